@@ -0,0 +1,96 @@
+#import bevy_pbr::mesh_view_bindings::view
+
+// The same material as `outline_toon.wgsl`, drawn once for every cube instance.
+struct ToonShaderOutlineMaterial {
+    color: vec4<f32>,
+    sun_dir: vec3<f32>,
+    sun_color: vec4<f32>,
+    camera_pos: vec3<f32>,
+    ambient_color: vec4<f32>,
+    outline_color: vec4<f32>
+};
+
+@group(1) @binding(0)
+var<uniform> material: ToonShaderOutlineMaterial;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+
+    @location(6) i_position_scale: vec4<f32>,
+    @location(7) i_rotation: vec4<f32>,
+    @location(8) i_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+// Rotates `v` by the quaternion `q`.
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let world_position = rotate(vertex.i_rotation, vertex.position * vertex.i_position_scale.w)
+        + vertex.i_position_scale.xyz;
+
+    var out: VertexOutput;
+    out.position = view.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = vec4<f32>(world_position, 1.0);
+    out.world_normal = rotate(vertex.i_rotation, vertex.normal);
+    out.uv = vertex.uv;
+    out.color = vertex.i_color;
+    return out;
+}
+
+@fragment
+fn fragment (in: VertexOutput) -> @location(0) vec4<f32> {
+    let base_color = material.color * in.color * textureSample(base_color_texture, base_color_sampler, in.uv);
+    let normal = normalize(in.world_normal);
+    let n_dot_l = dot(material.sun_dir, normal);
+    var light_intensity = 0.0;
+
+    if n_dot_l > 0.0 {
+        let bands = 3.0;
+        var x = n_dot_l * bands;
+
+        x = round(x);
+
+        light_intensity = x / bands;
+    } else {
+        light_intensity = 0.0;
+    }
+
+    let light = light_intensity * material.sun_color.rgb;
+
+    let view_dir: vec3<f32> = normalize(material.camera_pos - in.world_position.xyz);
+
+    let half_vector = normalize(material.sun_dir + view_dir);
+    let n_dot_h = dot(normal, half_vector);
+    let glossiness = 32.0;
+    let specular_intensity = pow(n_dot_h, glossiness * glossiness);
+
+    let specular_intensity_smooth = smoothstep(0.005, 0.01, specular_intensity);
+    let specular = specular_intensity_smooth * vec4<f32>(0.9, 0.9 ,0.9 , 0.0);
+
+    let rim_dot = 1.0 - dot(view_dir, normal);
+
+    if rim_dot > 0.5 && material.outline_color.a > 0.0 {
+        return material.outline_color * base_color.a;
+    } else {
+        return base_color * vec4<f32>(light.rgb + material.ambient_color.rgb + specular.rgb, 1.0);
+    }
+
+}
@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    input::{
+        mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+        InputSystem,
+    },
+    prelude::*,
+    utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// The name of the file the bindings are saved to.
+const BINDINGS_FILE: &str = "bindings";
+/// How many radians the camera turns per pixel of mouse movement.
+pub const MOUSE_SENSITIVITY: f32 = 0.001;
+/// How many scroll wheel lines per second holding a reel button is worth.
+pub const BUTTON_REEL_RATE: f32 = 10.0;
+
+/// Everything the player can do with a button, independent of what it's bound to.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Hook,
+    Dash,
+    SwitchHookMode,
+    ReelIn,
+    ReelOut,
+    Pause,
+    Shop,
+    Buy1,
+    Buy2,
+    Buy3,
+    ToggleInfo,
+}
+
+/// A single button an [`Action`] can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+impl Binding {
+    /// The path of the texture showing this binding, if we have one for it.
+    pub fn icon(&self) -> Option<String> {
+        let name = match self {
+            Binding::Key(key) => key_name(*key)?,
+            Binding::Mouse(MouseButton::Left) => "Mouse_Left",
+            Binding::Mouse(MouseButton::Right) => "Mouse_Right",
+            Binding::Mouse(MouseButton::Middle) => "Mouse_Middle",
+            Binding::Mouse(MouseButton::Other(_)) => "Mouse_Simple",
+            Binding::Gamepad(_) => return None,
+        };
+        Some(format!("textures/keyboardmouse/{name}_Key_Dark.png"))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+/// Which buttons and sticks trigger which [`Action`]s.
+///
+/// Loaded from and saved to the bindings file, so players can rebind their controls.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputBindings {
+    pub buttons: BTreeMap<Action, Vec<Binding>>,
+    pub move_stick: Option<Stick>,
+    pub look_stick: Option<Stick>,
+    /// How fast the camera turns when the look stick is fully pushed, in radians per second.
+    pub stick_sensitivity: f32,
+    /// How far a stick has to be pushed before it does anything.
+    pub stick_deadzone: f32,
+}
+impl Default for InputBindings {
+    fn default() -> Self {
+        use Binding::*;
+        Self {
+            buttons: BTreeMap::from([
+                (Action::MoveForward, vec![Key(KeyCode::W)]),
+                (Action::MoveBack, vec![Key(KeyCode::S)]),
+                (Action::MoveLeft, vec![Key(KeyCode::A)]),
+                (Action::MoveRight, vec![Key(KeyCode::D)]),
+                (
+                    Action::Jump,
+                    vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::South)],
+                ),
+                (
+                    Action::Hook,
+                    vec![
+                        Mouse(MouseButton::Left),
+                        Gamepad(GamepadButtonType::RightTrigger2),
+                    ],
+                ),
+                (
+                    Action::Dash,
+                    vec![
+                        Mouse(MouseButton::Right),
+                        Gamepad(GamepadButtonType::LeftTrigger2),
+                    ],
+                ),
+                (
+                    Action::SwitchHookMode,
+                    vec![Key(KeyCode::Q), Gamepad(GamepadButtonType::West)],
+                ),
+                (
+                    Action::ReelIn,
+                    vec![Gamepad(GamepadButtonType::RightTrigger)],
+                ),
+                (
+                    Action::ReelOut,
+                    vec![Gamepad(GamepadButtonType::LeftTrigger)],
+                ),
+                (
+                    Action::Pause,
+                    vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)],
+                ),
+                (
+                    Action::Shop,
+                    vec![Key(KeyCode::Tab), Gamepad(GamepadButtonType::Select)],
+                ),
+                (
+                    Action::Buy1,
+                    vec![Key(KeyCode::Key1), Gamepad(GamepadButtonType::DPadLeft)],
+                ),
+                (
+                    Action::Buy2,
+                    vec![Key(KeyCode::Key2), Gamepad(GamepadButtonType::DPadUp)],
+                ),
+                (
+                    Action::Buy3,
+                    vec![Key(KeyCode::Key3), Gamepad(GamepadButtonType::DPadRight)],
+                ),
+                (Action::ToggleInfo, vec![Key(KeyCode::F3)]),
+            ]),
+            move_stick: Some(Stick::Left),
+            look_stick: Some(Stick::Right),
+            stick_sensitivity: 3.0,
+            stick_deadzone: 0.15,
+        }
+    }
+}
+impl InputBindings {
+    pub const MIN_STICK_SENSITIVITY: f32 = 0.5;
+    pub const MAX_STICK_SENSITIVITY: f32 = 10.0;
+    pub const MAX_STICK_DEADZONE: f32 = 0.95;
+
+    /// The icon of the first binding of `action` that has one.
+    pub fn icon(&self, action: Action) -> Option<String> {
+        self.buttons.get(&action)?.iter().find_map(Binding::icon)
+    }
+    /// Binds any actions missing from a bindings file to their defaults,
+    /// so actions added after the file was written still work.
+    fn fill_missing(&mut self) {
+        for (action, bindings) in InputBindings::default().buttons {
+            self.buttons.entry(action).or_insert(bindings);
+        }
+    }
+    /// Brings the stick values back into their ranges, in case the bindings file was edited by hand.
+    fn clamp(mut self) -> Self {
+        let default = Self::default();
+        if self.stick_sensitivity.is_nan() {
+            self.stick_sensitivity = default.stick_sensitivity;
+        }
+        if self.stick_deadzone.is_nan() {
+            self.stick_deadzone = default.stick_deadzone;
+        }
+        self.stick_sensitivity = self
+            .stick_sensitivity
+            .clamp(Self::MIN_STICK_SENSITIVITY, Self::MAX_STICK_SENSITIVITY);
+        self.stick_deadzone = self.stick_deadzone.clamp(0.0, Self::MAX_STICK_DEADZONE);
+        self
+    }
+}
+
+/// The state of every [`Action`] for this frame, which gameplay systems read instead of raw input.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    /// Movement direction, `y` being forwards.
+    pub movement: Vec2,
+    /// Camera rotation for this frame in radians, `x` turning right and `y` turning up.
+    pub look: Vec2,
+    /// How far to reel in the rope this frame, in scroll wheel lines.
+    pub reel: f32,
+}
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// Input from controls that aren't bound buttons, like the on-screen touch controls.
+///
+/// Systems writing to this should run before [`ActionSet`], and it gets merged into the [`ActionState`].
+#[derive(Resource, Default)]
+pub struct VirtualInput {
+    pub pressed: HashSet<Action>,
+    previously_pressed: HashSet<Action>,
+    /// Movement direction, `y` being forwards.
+    pub movement: Vec2,
+    /// Camera rotation for this frame in radians, `x` turning right and `y` turning up.
+    pub look: Vec2,
+}
+
+/// Systems which update the [`ActionState`]. Read it after this set.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ActionSet;
+
+pub struct ActionsPlugin;
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        let bindings = match storage::load::<InputBindings>(BINDINGS_FILE) {
+            Some(mut bindings) => {
+                bindings.fill_missing();
+                bindings
+            }
+            None => {
+                let bindings = InputBindings::default();
+                storage::save(BINDINGS_FILE, &bindings);
+                bindings
+            }
+        };
+        app.insert_resource(bindings.clamp())
+            .init_resource::<ActionState>()
+            .init_resource::<VirtualInput>()
+            .add_systems(
+                PreUpdate,
+                update_action_state.in_set(ActionSet).after(InputSystem),
+            )
+            .add_systems(Update, save_bindings);
+    }
+}
+
+fn update_action_state(
+    mut state: ResMut<ActionState>,
+    mut virtual_input: ResMut<VirtualInput>,
+    bindings: Res<InputBindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    window: Query<&Window>,
+    time: Res<Time>,
+) {
+    let pressed = |binding: &Binding| match *binding {
+        Binding::Key(key) => keys.pressed(key),
+        Binding::Mouse(button) => mouse.pressed(button),
+        Binding::Gamepad(button_type) => gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.pressed(GamepadButton::new(gamepad, button_type))),
+    };
+    let just_pressed = |binding: &Binding| match *binding {
+        Binding::Key(key) => keys.just_pressed(key),
+        Binding::Mouse(button) => mouse.just_pressed(button),
+        Binding::Gamepad(button_type) => gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type))),
+    };
+    // Uses the stick that is pushed the furthest out of all connected gamepads.
+    let stick = |stick: Option<Stick>| {
+        let Some(stick) = stick else {
+            return Vec2::ZERO;
+        };
+        let (x, y) = match stick {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        };
+        gamepads
+            .iter()
+            .map(|gamepad| {
+                Vec2::new(
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, x))
+                        .unwrap_or(0.0),
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, y))
+                        .unwrap_or(0.0),
+                )
+            })
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or(Vec2::ZERO)
+    };
+
+    state.pressed.clear();
+    state.just_pressed.clear();
+    for (action, action_bindings) in &bindings.buttons {
+        if action_bindings.iter().any(pressed) {
+            state.pressed.insert(*action);
+        }
+        if action_bindings.iter().any(just_pressed) {
+            state.just_pressed.insert(*action);
+        }
+    }
+    for action in &virtual_input.pressed {
+        state.pressed.insert(*action);
+        if !virtual_input.previously_pressed.contains(action) {
+            state.just_pressed.insert(*action);
+        }
+    }
+    virtual_input.previously_pressed = virtual_input.pressed.clone();
+
+    let button_axis = |negative: Action, positive: Action| {
+        state.pressed(positive) as i32 as f32 - state.pressed(negative) as i32 as f32
+    };
+    let movement = Vec2::new(
+        button_axis(Action::MoveLeft, Action::MoveRight),
+        button_axis(Action::MoveBack, Action::MoveForward),
+    ) + apply_deadzone(stick(bindings.move_stick), bindings.stick_deadzone)
+        + virtual_input.movement;
+    let reel = button_axis(Action::ReelOut, Action::ReelIn) * BUTTON_REEL_RATE;
+    // Holding a key and tilting the stick at once shouldn't move any faster.
+    state.movement = movement.clamp_length_max(1.0);
+
+    let look_stick = apply_deadzone(stick(bindings.look_stick), bindings.stick_deadzone);
+    state.look =
+        look_stick * bindings.stick_sensitivity * time.delta_seconds() + virtual_input.look;
+
+    // The mouse only looks around while it's grabbed by the window.
+    let motion = motion
+        .read()
+        .fold(Vec2::ZERO, |vec2, event| vec2 + event.delta);
+    if window
+        .get_single()
+        .is_ok_and(|window| !window.cursor.visible)
+    {
+        state.look += Vec2::new(motion.x, -motion.y) * MOUSE_SENSITIVITY;
+    }
+
+    state.reel = reel * time.delta_seconds()
+        + wheel
+            .read()
+            .map(|event| match event.unit {
+                MouseScrollUnit::Line => event.y,
+                MouseScrollUnit::Pixel => event.y / 20.0,
+            })
+            .sum::<f32>();
+}
+
+/// Ignores stick positions within the deadzone, rescaling the rest so the stick still reaches `1.0`.
+fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= deadzone {
+        Vec2::ZERO
+    } else {
+        stick / length * ((length - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+fn save_bindings(bindings: Res<InputBindings>) {
+    if bindings.is_changed() && !bindings.is_added() {
+        storage::save(BINDINGS_FILE, &*bindings);
+    }
+}
+
+/// The name a key has in the `textures/keyboardmouse` icons.
+fn key_name(key: KeyCode) -> Option<&'static str> {
+    use KeyCode::*;
+    Some(match key {
+        Key0 => "0",
+        Key1 => "1",
+        Key2 => "2",
+        Key3 => "3",
+        Key4 => "4",
+        Key5 => "5",
+        Key6 => "6",
+        Key7 => "7",
+        Key8 => "8",
+        Key9 => "9",
+        A => "A",
+        B => "B",
+        C => "C",
+        D => "D",
+        E => "E",
+        F => "F",
+        G => "G",
+        H => "H",
+        I => "I",
+        J => "J",
+        K => "K",
+        L => "L",
+        M => "M",
+        N => "N",
+        O => "O",
+        P => "P",
+        Q => "Q",
+        R => "R",
+        S => "S",
+        T => "T",
+        U => "U",
+        V => "V",
+        W => "W",
+        X => "X",
+        Y => "Y",
+        Z => "Z",
+        F1 => "F1",
+        F2 => "F2",
+        F3 => "F3",
+        F4 => "F4",
+        F5 => "F5",
+        F6 => "F6",
+        F7 => "F7",
+        F8 => "F8",
+        F9 => "F9",
+        F10 => "F10",
+        F11 => "F11",
+        F12 => "F12",
+        Escape => "Esc",
+        Tab => "Tab",
+        Space => "Space",
+        Return | NumpadEnter => "Enter",
+        Back => "Backspace",
+        Delete => "Del",
+        Insert => "Insert",
+        Home => "Home",
+        End => "End",
+        PageUp => "Page_Up",
+        PageDown => "Page_Down",
+        Up => "Arrow_Up",
+        Down => "Arrow_Down",
+        Left => "Arrow_Left",
+        Right => "Arrow_Right",
+        ShiftLeft | ShiftRight => "Shift",
+        ControlLeft | ControlRight => "Ctrl",
+        AltLeft | AltRight => "Alt",
+        SuperLeft | SuperRight => "Win",
+        Capital => "Caps_Lock",
+        Numlock => "Num_Lock",
+        Snapshot => "Print_Screen",
+        Minus | NumpadSubtract => "Minus",
+        Plus | NumpadAdd => "Plus",
+        Asterisk | NumpadMultiply => "Asterisk",
+        Semicolon => "Semicolon",
+        Apostrophe => "Quote",
+        Slash => "Slash",
+        Grave => "Tilda",
+        BracketLeft => "Bracket_Left",
+        BracketRight => "Bracket_Right",
+        _ => return None,
+    })
+}
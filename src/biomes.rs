@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+use bevy_atmosphere::{collection::gradient::Gradient, model::AtmosphereModel};
+use bevy_toon_shader::ToonShaderSun;
+use bevy_xpbd_3d::resources::Gravity;
+use rand::Rng;
+
+use crate::{player::Player, spawn_table::SpawnTable, GameState};
+
+/// How far around the border between two biomes their visuals are blended, in units of height.
+pub const BIOME_BLEND_DISTANCE: f32 = 40.0;
+
+/// A layer of the world, starting at a certain height.
+pub struct Biome {
+    pub name: &'static str,
+    /// The height this biome starts at, it lasts until the next one starts.
+    pub from_height: f32,
+    pub sky: Color,
+    pub horizon: Color,
+    pub ground: Color,
+    pub ambient_color: Color,
+    pub sun_color: Color,
+    /// Multiplies the strength of gravity.
+    pub gravity_scale: f32,
+    /// The range the colors of gumballs and thingamajigs are picked from.
+    pub object_colors: ColorRange,
+    /// The path of the spawn table used in this biome.
+    pub spawn_table: &'static str,
+}
+
+/// A range of colors, picked from per channel in linear space.
+pub struct ColorRange {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+impl ColorRange {
+    pub fn sample(&self, rng: &mut impl Rng) -> Color {
+        let mut channel = |i: usize| {
+            if self.min[i] < self.max[i] {
+                rng.gen_range(self.min[i]..self.max[i])
+            } else {
+                self.min[i]
+            }
+        };
+        Color::rgb_linear(channel(0), channel(1), channel(2))
+    }
+}
+
+/// The biomes from the ground up, sorted by their starting height.
+pub const BIOMES: [Biome; 4] = [
+    Biome {
+        name: "Candy Shop",
+        from_height: f32::NEG_INFINITY,
+        sky: Color::rgb(0.6, 0.8, 0.9),
+        horizon: Color::rgb(0.8, 0.7, 0.9),
+        ground: Color::GRAY,
+        ambient_color: Color::WHITE,
+        sun_color: Color::WHITE,
+        gravity_scale: 1.0,
+        object_colors: ColorRange {
+            min: [0.3, 0.3, 0.3],
+            max: [10.0, 10.0, 10.0],
+        },
+        spawn_table: "candy_shop.spawn_table.ron",
+    },
+    Biome {
+        name: "Clouds",
+        from_height: 150.0,
+        sky: Color::rgb(0.75, 0.88, 0.98),
+        horizon: Color::rgb(0.97, 0.95, 1.0),
+        ground: Color::rgb(0.9, 0.92, 0.95),
+        ambient_color: Color::rgb(0.95, 0.97, 1.0),
+        sun_color: Color::rgb(1.0, 0.97, 0.9),
+        gravity_scale: 0.9,
+        object_colors: ColorRange {
+            min: [2.0, 2.0, 2.0],
+            max: [10.0, 10.0, 10.0],
+        },
+        spawn_table: "clouds.spawn_table.ron",
+    },
+    Biome {
+        name: "Stratosphere",
+        from_height: 400.0,
+        sky: Color::rgb(0.2, 0.35, 0.7),
+        horizon: Color::rgb(0.55, 0.7, 0.95),
+        ground: Color::rgb(0.7, 0.8, 0.95),
+        ambient_color: Color::rgb(0.8, 0.85, 1.0),
+        sun_color: Color::rgb(1.0, 0.95, 0.85),
+        gravity_scale: 0.75,
+        object_colors: ColorRange {
+            min: [0.3, 0.8, 3.0],
+            max: [3.0, 6.0, 10.0],
+        },
+        spawn_table: "stratosphere.spawn_table.ron",
+    },
+    Biome {
+        name: "Space",
+        from_height: 800.0,
+        sky: Color::rgb(0.01, 0.01, 0.04),
+        horizon: Color::rgb(0.08, 0.05, 0.2),
+        ground: Color::rgb(0.15, 0.2, 0.4),
+        ambient_color: Color::rgb(0.6, 0.55, 0.9),
+        sun_color: Color::rgb(1.0, 1.0, 1.0),
+        gravity_scale: 0.5,
+        object_colors: ColorRange {
+            min: [2.0, 0.3, 2.0],
+            max: [10.0, 3.0, 10.0],
+        },
+        spawn_table: "space.spawn_table.ron",
+    },
+];
+
+/// The biome the player is currently in.
+#[derive(Resource, Default)]
+pub struct CurrentBiome(pub usize);
+
+/// The handles keeping the spawn table of every biome loaded, in the same order as [`BIOMES`].
+#[derive(Resource)]
+pub struct BiomeSpawnTables(pub Vec<Handle<SpawnTable>>);
+impl FromWorld for BiomeSpawnTables {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(
+            BIOMES
+                .iter()
+                .map(|biome| asset_server.load(biome.spawn_table))
+                .collect(),
+        )
+    }
+}
+
+pub struct BiomePlugin;
+
+/// This plugin changes the look and feel of the world as the player climbs through the [`BIOMES`]
+impl Plugin for BiomePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentBiome>()
+            .init_resource::<BiomeSpawnTables>()
+            .add_systems(OnEnter(GameState::Menu), reset_biome)
+            .add_systems(Update, update_biome.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// The index of the biome at `height`, without any blending.
+pub fn biome_at(height: f32) -> usize {
+    BIOMES
+        .iter()
+        .rposition(|biome| biome.from_height <= height)
+        .unwrap_or(0)
+}
+
+/// Finds the biome at `height`, along with the next one and how far it's blended into it.
+fn biome_blend(height: f32) -> (usize, usize, f32) {
+    let index = biome_at(height);
+    // Blending starts half way before the border and ends half way after it.
+    if let Some(next) = BIOMES.get(index + 1) {
+        let t = (height - (next.from_height - BIOME_BLEND_DISTANCE / 2.0)) / BIOME_BLEND_DISTANCE;
+        if t > 0.0 {
+            return (index, index + 1, smoothstep(t));
+        }
+    }
+    if index > 0 {
+        let t = (height - (BIOMES[index].from_height - BIOME_BLEND_DISTANCE / 2.0))
+            / BIOME_BLEND_DISTANCE;
+        if t < 1.0 {
+            return (index - 1, index, smoothstep(t));
+        }
+    }
+    (index, index, 0.0)
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Vec4::from(a.as_rgba_f32())
+        .lerp(Vec4::from(b.as_rgba_f32()), t)
+        .into()
+}
+
+fn update_biome(
+    player: Query<&Transform, With<Player>>,
+    mut current: ResMut<CurrentBiome>,
+    mut atmosphere: ResMut<AtmosphereModel>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut gravity: ResMut<Gravity>,
+    mut sun: Query<&mut DirectionalLight, With<ToonShaderSun>>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+    let (from, to, t) = biome_blend(player_transform.translation.y);
+    let index = if t < 0.5 { from } else { to };
+    if current.0 != index {
+        info!("Entering the {} biome", BIOMES[index].name);
+        current.0 = index;
+    }
+    apply_biome(
+        &BIOMES[from],
+        &BIOMES[to],
+        t,
+        &mut atmosphere,
+        &mut clear_color,
+        &mut ambient_light,
+        &mut gravity,
+        sun.get_single_mut().ok().as_deref_mut(),
+    );
+}
+
+fn reset_biome(
+    mut current: ResMut<CurrentBiome>,
+    mut atmosphere: ResMut<AtmosphereModel>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut gravity: ResMut<Gravity>,
+) {
+    current.0 = 0;
+    apply_biome(
+        &BIOMES[0],
+        &BIOMES[0],
+        0.0,
+        &mut atmosphere,
+        &mut clear_color,
+        &mut ambient_light,
+        &mut gravity,
+        None,
+    );
+}
+
+/// Sets the visuals and gravity to a blend between two biomes.
+/// Resources are only touched when they actually change, since changing the atmosphere redraws the sky.
+#[allow(clippy::too_many_arguments)]
+fn apply_biome(
+    from: &Biome,
+    to: &Biome,
+    t: f32,
+    atmosphere: &mut ResMut<AtmosphereModel>,
+    clear_color: &mut ResMut<ClearColor>,
+    ambient_light: &mut ResMut<AmbientLight>,
+    gravity: &mut ResMut<Gravity>,
+    sun: Option<&mut DirectionalLight>,
+) {
+    let gradient = Gradient {
+        sky: lerp_color(from.sky, to.sky, t),
+        horizon: lerp_color(from.horizon, to.horizon, t),
+        ground: lerp_color(from.ground, to.ground, t),
+    };
+    let unchanged = atmosphere.to_ref::<Gradient>().is_some_and(|current| {
+        current.sky == gradient.sky
+            && current.horizon == gradient.horizon
+            && current.ground == gradient.ground
+    });
+    if !unchanged {
+        clear_color.0 = gradient.sky;
+        **atmosphere = AtmosphereModel::new(gradient);
+    }
+    let ambient_color = lerp_color(from.ambient_color, to.ambient_color, t);
+    if ambient_light.color != ambient_color {
+        ambient_light.color = ambient_color;
+    }
+    let new_gravity =
+        Vec3::NEG_Y * (from.gravity_scale + (to.gravity_scale - from.gravity_scale) * t);
+    if gravity.0 != new_gravity {
+        gravity.0 = new_gravity;
+    }
+    if let Some(sun) = sun {
+        sun.color = lerp_color(from.sun_color, to.sun_color, t);
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::Rng;
+
+use crate::{
+    biomes::{biome_at, BiomeSpawnTables},
+    gameplay_running,
+    gumball_pool::{GumballPool, Pooled},
+    player::Player,
+    seed::WorldRng,
+    spawn_table::{Archetype, SpawnTable, SpawnVolume},
+    spawning::ObjectSpawner,
+};
+
+/// The size of a chunk along every axis.
+pub const CHUNK_SIZE: f32 = 150.0;
+/// Chunks up to this many chunks away from the player's chunk along every axis get loaded.
+pub const LOAD_DISTANCE: i32 = 1;
+/// Chunks further away than this many chunks get unloaded.
+/// It's further than `LOAD_DISTANCE` so going back and forth over a border doesn't keep reloading chunks.
+pub const UNLOAD_DISTANCE: i32 = 2;
+
+/// The world around the player, split into a grid of cubic chunks.
+///
+/// Every chunk is generated from its own random number generator when it's loaded,
+/// so it looks the same every time for the same seed.
+/// Everything spawned in a chunk gets despawned once it's unloaded.
+#[derive(Resource, Default)]
+pub struct WorldChunks {
+    /// The chunks which have been generated.
+    generated: HashSet<IVec3>,
+    /// The entities spawned in each chunk.
+    entities: HashMap<IVec3, Vec<Entity>>,
+    /// The chunk each entity was last added to.
+    ///
+    /// Pooled gumballs get reused in other chunks, so they're only unloaded along with the chunk owning them.
+    owners: HashMap<Entity, IVec3>,
+}
+impl WorldChunks {
+    /// The chunk containing `position`.
+    pub fn chunk_at(position: Vec3) -> IVec3 {
+        (position / CHUNK_SIZE).floor().as_ivec3()
+    }
+    /// Adds entities to a chunk, so they get despawned along with it.
+    pub fn add(&mut self, chunk: IVec3, entities: impl IntoIterator<Item = Entity>) {
+        let list = self.entities.entry(chunk).or_default();
+        for entity in entities {
+            list.push(entity);
+            self.owners.insert(entity, chunk);
+        }
+    }
+}
+
+pub struct ChunkPlugin;
+
+/// This plugin loads the chunks around the player and unloads the ones left behind.
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldChunks>().add_systems(
+            Update,
+            (unload_chunks, load_chunks)
+                .chain()
+                .run_if(gameplay_running),
+        );
+    }
+}
+
+fn load_chunks(
+    mut spawner: ObjectSpawner,
+    player: Query<&Transform, With<Player>>,
+    spawn_tables: Res<Assets<SpawnTable>>,
+    biome_spawn_tables: Res<BiomeSpawnTables>,
+    world_rng: Res<WorldRng>,
+    mut chunks: ResMut<WorldChunks>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+    let center = WorldChunks::chunk_at(player_transform.translation);
+    for x in -LOAD_DISTANCE..=LOAD_DISTANCE {
+        for y in -LOAD_DISTANCE..=LOAD_DISTANCE {
+            for z in -LOAD_DISTANCE..=LOAD_DISTANCE {
+                let chunk = center + IVec3::new(x, y, z);
+                if chunks.generated.contains(&chunk) {
+                    continue;
+                }
+                let origin = chunk.as_vec3() * CHUNK_SIZE;
+                let height = origin.y + CHUNK_SIZE / 2.0;
+                let biome = biome_at(height);
+                // Chunks are only generated once everything they need is loaded, otherwise they'd turn out different.
+                let Some(spawn_table) = spawn_tables.get(&biome_spawn_tables.0[biome]) else {
+                    continue;
+                };
+                let structures_loaded = spawn_table
+                    .chunk_objects
+                    .iter()
+                    .flat_map(|object| &object.structure_handles)
+                    .all(|handle| spawner.structures.contains(handle));
+                if !structures_loaded {
+                    continue;
+                }
+
+                let mut rand = world_rng.chunk(chunk);
+                let mut placed: Vec<(Archetype, Vec3)> = Vec::new();
+                let mut entities = Vec::new();
+                for object in &spawn_table.chunk_objects {
+                    // Keeping half the spacing away from the borders keeps objects in different chunks apart,
+                    // without having to know what's in the neighbouring chunks.
+                    let margin = (object.min_spacing / 2.0).min(CHUNK_SIZE / 2.0);
+                    let volume = SpawnVolume {
+                        min: origin + margin,
+                        max: origin + CHUNK_SIZE - margin,
+                    };
+                    for _ in 0..object.attempts {
+                        let pos = volume.sample(&mut rand);
+                        if rand.gen::<f32>() >= object.chance(height)
+                            || object.from_height.is_some_and(|from| pos.y < from)
+                            || placed.iter().any(|(archetype, other)| {
+                                *archetype == object.archetype
+                                    && other.distance(pos) < object.min_spacing
+                            })
+                        {
+                            continue;
+                        }
+                        placed.push((object.archetype, pos));
+                        entities.extend(spawner.spawn(object.settings(), biome, &mut rand, pos));
+                    }
+                }
+                chunks.generated.insert(chunk);
+                chunks.add(chunk, entities);
+            }
+        }
+    }
+}
+
+fn unload_chunks(
+    mut commands: Commands,
+    player: Query<&Transform, With<Player>>,
+    existing: Query<Has<Pooled>>,
+    mut chunks: ResMut<WorldChunks>,
+    mut pool: ResMut<GumballPool>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+    let center = WorldChunks::chunk_at(player_transform.translation);
+    let far = |chunk: &IVec3| (*chunk - center).abs().max_element() > UNLOAD_DISTANCE;
+    chunks.generated.retain(|chunk| !far(chunk));
+    let WorldChunks {
+        entities, owners, ..
+    } = &mut *chunks;
+    entities.retain(|chunk, entities| {
+        // Objects can also be despawned some other way, like falling too far,
+        // and pooled gumballs can be taken out of the pool again for another chunk.
+        entities.retain(|entity| {
+            if owners.get(entity) != Some(chunk) {
+                return false;
+            }
+            if existing.contains(*entity) {
+                return true;
+            }
+            owners.remove(entity);
+            false
+        });
+        if !far(chunk) {
+            return true;
+        }
+        for entity in entities.iter() {
+            owners.remove(entity);
+            if existing.get(*entity).unwrap_or_default() {
+                pool.release(&mut commands, *entity);
+            } else {
+                commands.entity(*entity).despawn();
+            }
+        }
+        false
+    });
+}
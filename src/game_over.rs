@@ -0,0 +1,255 @@
+use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
+use bevy_xpbd_3d::prelude::*;
+
+use crate::{
+    gameplay_running,
+    high_scores::{record_high_score, spawn_high_score_table, HighScores},
+    hud::Score,
+    menu::ButtonColors,
+    pause::release_cursor,
+    pickups::Coins,
+    player::Player,
+    seed::WorldRng,
+    GameState,
+};
+
+pub struct GameOverPlugin;
+
+/// This plugin ends the run once the [`GameOverRule`] is broken and shows a summary of it
+/// The summary is only drawn during the State `GameState::GameOver`
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameOverRule>()
+            .init_resource::<RunStats>()
+            .add_systems(
+                Update,
+                (update_run_stats, check_game_over)
+                    .chain()
+                    .run_if(gameplay_running),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (record_high_score, setup_game_over).chain(),
+            )
+            .add_systems(
+                Update,
+                click_game_over_buttons.run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnExit(GameState::GameOver), cleanup_game_over);
+    }
+}
+
+/// Decides when a run is lost.
+#[derive(Resource)]
+pub struct GameOverRule {
+    /// How far the player can fall below the highest point they reached before the run ends.
+    ///
+    /// Set to [`f32::INFINITY`] for runs that never end.
+    pub max_fall: f32,
+}
+impl Default for GameOverRule {
+    fn default() -> Self {
+        Self { max_fall: 100.0 }
+    }
+}
+
+/// Stats about the current run, shown on the game over screen.
+#[derive(Resource, Default)]
+pub struct RunStats {
+    /// The highest Y position the player reached.
+    pub best_height: f32,
+    /// How long the run has been going on for, in seconds. Doesn't count while physics is paused.
+    pub time: f32,
+    /// The fastest the player has moved, in units per second.
+    pub peak_speed: f32,
+}
+
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    Retry,
+    MainMenu,
+}
+
+fn update_run_stats(
+    mut stats: ResMut<RunStats>,
+    player: Query<(&Transform, &LinearVelocity), With<Player>>,
+    physics_time: Res<Time<Physics>>,
+) {
+    if physics_time.is_paused() {
+        return;
+    }
+    stats.time += physics_time.delta_seconds();
+    if let Ok((transform, velocity)) = player.get_single() {
+        stats.best_height = stats.best_height.max(transform.translation.y);
+        stats.peak_speed = stats.peak_speed.max(velocity.length());
+    }
+}
+
+fn check_game_over(
+    rule: Res<GameOverRule>,
+    stats: Res<RunStats>,
+    player: Query<&Transform, With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let Ok(transform) = player.get_single() {
+        if transform.translation.y < stats.best_height - rule.max_fall {
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut window: Query<&mut Window>,
+    mut physics_time: ResMut<Time<Physics>>,
+    score: Res<Score>,
+    coins: Res<Coins>,
+    stats: Res<RunStats>,
+    high_scores: Res<HighScores>,
+    world_rng: Res<WorldRng>,
+) {
+    if let Ok(mut window) = window.get_single_mut() {
+        release_cursor(&mut window);
+    }
+    physics_time.pause();
+
+    let title_style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 75.0,
+        color: Color::WHITE,
+    };
+    let stat_style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 30.0,
+        color: Color::WHITE,
+    };
+    let button_style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let score = score.final_score();
+    let lines = [
+        format!("Height: {}", score.height),
+        format!("Destruction: {}", score.destruction),
+        format!("Coins collected: {}", coins.collected),
+        format!("Coins spent: {}", coins.spent),
+        format!(
+            "Run time: {}:{:02}",
+            stats.time as u64 / 60,
+            stats.time as u64 % 60
+        ),
+        format!("Peak speed: {:.1} m/s", stats.peak_speed),
+        format!("Seed: {}", world_rng.seed),
+    ];
+
+    // Drawn on top of the game, which stays visible behind the summary.
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::None,
+            },
+            ..default()
+        },
+        GameOverScreen,
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.75)),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section("GAME OVER", title_style));
+            for line in lines {
+                commands.spawn(TextBundle::from_section(line, stat_style.clone()));
+            }
+            spawn_high_score_table(
+                commands,
+                &high_scores,
+                TextStyle {
+                    font_size: 20.0,
+                    ..stat_style.clone()
+                },
+            );
+            for (button, text) in [
+                (GameOverButton::Retry, "Retry"),
+                (GameOverButton::MainMenu, "Main Menu"),
+            ] {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                min_width: Val::Px(280.0),
+                                height: Val::Px(55.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: ButtonColors::default().normal.into(),
+                            ..default()
+                        },
+                        ButtonColors::default(),
+                        button,
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(text, button_style.clone()));
+                    });
+            }
+        });
+}
+
+fn click_game_over_buttons(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &GameOverButton,
+        ),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => next_state.set(match button {
+                GameOverButton::Retry => GameState::Restarting,
+                GameOverButton::MainMenu => GameState::Menu,
+            }),
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn cleanup_game_over(mut commands: Commands, screen: Query<Entity, With<GameOverScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+
+/// How bouncy [`GumballKind::Bouncy`] gumballs are, 1 would keep all of the speed.
+pub const BOUNCY_RESTITUTION: f32 = 0.95;
+/// How far from an exploding gumball things get pushed away.
+pub const EXPLOSION_RADIUS: f32 = 20.0;
+/// The speed things right next to an exploding gumball get pushed away with, it falls off with the distance.
+pub const EXPLOSION_STRENGTH: f32 = 60.0;
+
+/// What a gumball does when the player touches or hooks it, picked from the `gumball_kinds` of a spawn table entry.
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum GumballKind {
+    /// Doesn't do anything special, its color comes from the biome.
+    #[default]
+    Plain,
+    /// Bounces off everything, including the player.
+    Bouncy,
+    /// The player sticks to it until they jump or hook something.
+    Sticky,
+    /// Explodes when it's touched or hooked, pushing everything around it away.
+    Explosive,
+    /// Refills the dash of the player touching it.
+    Boost,
+    /// A hazard, knocks the player away and breaks their hook.
+    Spiked,
+}
+impl GumballKind {
+    pub const SPECIAL: [GumballKind; 5] = [
+        GumballKind::Bouncy,
+        GumballKind::Sticky,
+        GumballKind::Explosive,
+        GumballKind::Boost,
+        GumballKind::Spiked,
+    ];
+
+    /// Picks a kind from `kinds` based on their weights, or a plain one if there are none.
+    pub fn pick(kinds: &[(GumballKind, f32)], rand: &mut impl Rng) -> Self {
+        match WeightedIndex::new(kinds.iter().map(|(_, weight)| *weight)) {
+            Ok(weights) => kinds[weights.sample(rand)].0,
+            Err(_) => GumballKind::Plain,
+        }
+    }
+    /// The color and outline showing the kind of a gumball, plain ones use the colors of their biome instead.
+    pub fn colors(self) -> Option<(Color, Color)> {
+        match self {
+            GumballKind::Plain => None,
+            GumballKind::Bouncy => Some((
+                Color::rgb_linear(0.3, 4.0, 0.5),
+                Color::rgb_linear(0.5, 8.0, 1.0),
+            )),
+            GumballKind::Sticky => Some((
+                Color::rgb_linear(3.0, 0.3, 4.0),
+                Color::rgb_linear(6.0, 1.0, 8.0),
+            )),
+            GumballKind::Explosive => Some((
+                Color::rgb_linear(6.0, 0.2, 0.1),
+                Color::rgb_linear(10.0, 4.0, 0.0),
+            )),
+            GumballKind::Boost => Some((
+                Color::rgb_linear(0.2, 3.0, 6.0),
+                Color::rgb_linear(1.0, 8.0, 10.0),
+            )),
+            GumballKind::Spiked => Some((
+                Color::rgb_linear(0.05, 0.05, 0.05),
+                Color::rgb_linear(12.0, 0.0, 0.0),
+            )),
+        }
+    }
+    pub fn restitution(self) -> Restitution {
+        match self {
+            GumballKind::Bouncy => {
+                Restitution::new(BOUNCY_RESTITUTION).with_combine_rule(CoefficientCombine::Max)
+            }
+            _ => Restitution::default(),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_xpbd_3d::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    biomes::BIOMES, gumball_kinds::GumballKind, materials::OutlineToonMaterial,
+    spawn_table::Archetype, spawning::DespawnOnLowerThanY, GameState,
+};
+
+/// How many colors the gumballs of each biome are picked from.
+pub const PALETTE_SIZE: usize = 32;
+/// The outline of a hooked gumball.
+pub const HOOK_OUTLINE_COLOR: Color = Color::rgb_linear(100.0, 100.0, 100.0);
+
+/// Gumballs which fell out of the world, along with the materials shared by all gumballs.
+///
+/// Gumballs are spawned and fall out of the world constantly,
+/// so instead of being despawned they're hidden and spawned again from here.
+#[derive(Resource)]
+pub struct GumballPool {
+    free: HashSet<Entity>,
+    /// The materials of every biome's plain gumballs, in the same order as the biomes.
+    palettes: Vec<Vec<PaletteMaterial>>,
+    /// The materials of the special kinds of gumballs, which look the same in every biome.
+    kinds: HashMap<GumballKind, PaletteMaterial>,
+}
+impl FromWorld for GumballPool {
+    fn from_world(world: &mut World) -> Self {
+        let mut toon_materials = world.resource_mut::<Assets<OutlineToonMaterial>>();
+        let mut material = |color, outline_color| {
+            toon_materials.add(OutlineToonMaterial {
+                color,
+                outline_color,
+                ..default()
+            })
+        };
+        let palettes = BIOMES
+            .iter()
+            .enumerate()
+            .map(|(i, biome)| {
+                // The palettes don't depend on the world seed, only which of their colors gets picked does.
+                let mut rand = ChaCha8Rng::seed_from_u64(i as u64);
+                (0..PALETTE_SIZE)
+                    .map(|_| {
+                        let color = biome.object_colors.sample(&mut rand);
+                        PaletteMaterial {
+                            plain: material(color, Color::NONE),
+                            outlined: material(color, HOOK_OUTLINE_COLOR),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let kinds = GumballKind::SPECIAL
+            .into_iter()
+            .filter_map(|kind| {
+                let (color, outline_color) = kind.colors()?;
+                Some((
+                    kind,
+                    PaletteMaterial {
+                        plain: material(color, outline_color),
+                        outlined: material(color, HOOK_OUTLINE_COLOR),
+                    },
+                ))
+            })
+            .collect();
+        Self {
+            free: HashSet::new(),
+            palettes,
+            kinds,
+        }
+    }
+}
+impl GumballPool {
+    /// The material of a gumball of `kind`, plain ones get theirs picked from the palette of `biome`.
+    pub fn material(
+        &self,
+        kind: GumballKind,
+        biome: usize,
+        rand: &mut impl Rng,
+    ) -> PaletteMaterial {
+        if let Some(material) = self.kinds.get(&kind) {
+            return material.clone();
+        }
+        let palette = &self.palettes[biome];
+        palette[rand.gen_range(0..palette.len())].clone()
+    }
+    /// Takes a gumball out of the pool, if there are any left.
+    pub fn take(&mut self) -> Option<Entity> {
+        let entity = *self.free.iter().next()?;
+        self.free.remove(&entity);
+        Some(entity)
+    }
+    /// Hides a gumball and puts it in the pool, unless it's already in there.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if !self.free.insert(entity) {
+            return;
+        }
+        // Without a collider it can't be hit or hooked, and it's no longer in the spatial hash without an archetype.
+        commands
+            .entity(entity)
+            .insert((RigidBody::Static, Visibility::Hidden))
+            .remove::<(Collider, Archetype, DespawnOnLowerThanY)>();
+    }
+}
+
+/// A material shared by gumballs of the same color, along with its outlined twin used while a gumball is hooked.
+#[derive(Component, Clone)]
+pub struct PaletteMaterial {
+    pub plain: Handle<OutlineToonMaterial>,
+    pub outlined: Handle<OutlineToonMaterial>,
+}
+
+/// Marks gumballs which go back into the [`GumballPool`] instead of being despawned.
+#[derive(Component)]
+pub struct Pooled;
+
+pub struct GumballPoolPlugin;
+
+/// This plugin sets up the [`GumballPool`].
+impl Plugin for GumballPoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GumballPool>()
+            .add_systems(OnEnter(GameState::Playing), clear_pool);
+    }
+}
+
+/// The pooled gumballs get despawned with everything else at the end of a run.
+fn clear_pool(mut pool: ResMut<GumballPool>) {
+    pool.free.clear();
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{
+    gameplay_running,
+    gumball_pool::{PaletteMaterial, HOOK_OUTLINE_COLOR},
+    materials::OutlineToonMaterial,
+};
+
+/// How long the hook can't be used after touching an electric sphere, in seconds.
+pub const ELECTRIC_STUN: f32 = 3.0;
+/// How long an electric sphere can't be hooked after zapping the hook, in seconds.
+pub const ELECTRIC_COOLDOWN: f32 = 3.0;
+/// How long the hook can't be used after hitting a spiked gumball, in seconds.
+pub const SPIKE_STUN: f32 = 1.0;
+/// How fast a spiked gumball knocks the player away from it.
+pub const SPIKE_KNOCKBACK: f32 = 40.0;
+/// How fast an anvil pushes the player down at least.
+pub const ANVIL_PUSH: f32 = 40.0;
+/// How fast anvils fall when they spawn.
+pub const ANVIL_SPEED: f32 = 30.0;
+/// The size of anvils along every axis.
+pub const ANVIL_SIZE: Vec3 = Vec3::new(4.0, 2.0, 3.0);
+/// How many times a second the outline of electric spheres flickers.
+pub const ELECTRIC_FLICKER_RATE: f32 = 6.0;
+
+const ELECTRIC_COLOR: Color = Color::rgb_linear(3.0, 3.0, 0.3);
+const ELECTRIC_OUTLINE_COLOR: Color = Color::rgb_linear(20.0, 20.0, 2.0);
+const ANVIL_COLOR: Color = Color::rgb_linear(0.05, 0.05, 0.06);
+const ANVIL_OUTLINE_COLOR: Color = Color::rgb_linear(10.0, 2.0, 0.0);
+
+/// The materials shared by all hazards of the same archetype, their outlines warn the player about them.
+#[derive(Resource)]
+pub struct HazardMaterials {
+    pub electric: PaletteMaterial,
+    pub anvil: PaletteMaterial,
+}
+impl FromWorld for HazardMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut toon_materials = world.resource_mut::<Assets<OutlineToonMaterial>>();
+        let mut material = |color, outline_color| PaletteMaterial {
+            plain: toon_materials.add(OutlineToonMaterial {
+                color,
+                outline_color,
+                ..default()
+            }),
+            outlined: toon_materials.add(OutlineToonMaterial {
+                color,
+                outline_color: HOOK_OUTLINE_COLOR,
+                ..default()
+            }),
+        };
+        Self {
+            electric: material(ELECTRIC_COLOR, ELECTRIC_OUTLINE_COLOR),
+            anvil: material(ANVIL_COLOR, ANVIL_OUTLINE_COLOR),
+        }
+    }
+}
+
+/// Keeps an electric sphere from being hooked until the timer finishes.
+#[derive(Component)]
+pub struct ElectricCooldown(pub Timer);
+impl ElectricCooldown {
+    pub fn new() -> Self {
+        Self(Timer::from_seconds(ELECTRIC_COOLDOWN, TimerMode::Once))
+    }
+}
+
+pub struct HazardPlugin;
+
+/// This plugin sets up the materials of hazards and animates them.
+/// What hazards do to the player is handled by the player systems.
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HazardMaterials>().add_systems(
+            Update,
+            (flicker_electric_spheres, tick_electric_cooldowns).run_if(gameplay_running),
+        );
+    }
+}
+
+fn flicker_electric_spheres(
+    hazard_materials: Res<HazardMaterials>,
+    mut toon_materials: ResMut<Assets<OutlineToonMaterial>>,
+    time: Res<Time>,
+) {
+    if let Some(material) = toon_materials.get_mut(&hazard_materials.electric.plain) {
+        let flicker = (time.elapsed_seconds() * ELECTRIC_FLICKER_RATE * std::f32::consts::TAU)
+            .sin()
+            .abs();
+        material.outline_color = ELECTRIC_OUTLINE_COLOR * flicker;
+    }
+}
+
+fn tick_electric_cooldowns(
+    mut commands: Commands,
+    mut cooldowns: Query<(Entity, &mut ElectricCooldown)>,
+    time: Res<Time>,
+) {
+    for (entity, mut cooldown) in &mut cooldowns {
+        if cooldown.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<ElectricCooldown>();
+        }
+    }
+}
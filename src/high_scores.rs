@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hud::Score,
+    player::{Player, PlayerUpgrades},
+    storage,
+};
+
+/// The name of the file the high scores are saved to.
+const HIGH_SCORES_FILE: &str = "high_scores";
+/// How many runs are kept in the high score table.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+/// The best runs, sorted from highest to lowest total score.
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    pub runs: Vec<HighScore>,
+    /// The index of the run that just ended, if it made it into the table.
+    #[serde(skip)]
+    pub latest: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub total: u64,
+    pub height: u64,
+    pub destruction: u64,
+    /// When the run ended, in seconds since the unix epoch.
+    pub timestamp: u64,
+    pub upgrades: PlayerUpgrades,
+}
+
+pub struct HighScoresPlugin;
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load_data::<HighScores>(HIGH_SCORES_FILE).unwrap_or_default());
+    }
+}
+
+/// Adds the run that just ended to the high score table and saves it.
+pub fn record_high_score(
+    mut high_scores: ResMut<HighScores>,
+    score: Res<Score>,
+    player: Query<&Player>,
+) {
+    let score = score.final_score();
+    let run = HighScore {
+        total: score.total(),
+        height: score.height,
+        destruction: score.destruction,
+        timestamp: now(),
+        upgrades: player
+            .get_single()
+            .map(|player| player.upgrades)
+            .unwrap_or_default(),
+    };
+    // Ties go to the older run.
+    let index = high_scores
+        .runs
+        .iter()
+        .position(|other| other.total < run.total)
+        .unwrap_or(high_scores.runs.len());
+    high_scores.latest = (index < MAX_HIGH_SCORES).then_some(index);
+    if high_scores.latest.is_some() {
+        high_scores.runs.insert(index, run);
+        high_scores.runs.truncate(MAX_HIGH_SCORES);
+        storage::save_data(HIGH_SCORES_FILE, &*high_scores);
+    }
+}
+
+/// Spawns the high score table as a column of text, with the latest run highlighted.
+pub fn spawn_high_score_table(
+    commands: &mut ChildBuilder,
+    high_scores: &HighScores,
+    style: TextStyle,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section("High Scores", style.clone()));
+            if high_scores.runs.is_empty() {
+                commands.spawn(TextBundle::from_section("No runs yet", style.clone()));
+            }
+            for (i, run) in high_scores.runs.iter().enumerate() {
+                let color = if high_scores.latest == Some(i) {
+                    Color::YELLOW
+                } else {
+                    style.color
+                };
+                commands.spawn(TextBundle::from_section(
+                    format!(
+                        "{}. {} ({} height, {} destruction) {}",
+                        i + 1,
+                        run.total,
+                        run.height,
+                        run.destruction,
+                        format_date(run.timestamp)
+                    ),
+                    TextStyle {
+                        color,
+                        ..style.clone()
+                    },
+                ));
+            }
+        });
+}
+
+/// The current time, in seconds since the unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// `SystemTime` isn't available on the web.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// Formats a unix timestamp as a `YYYY-MM-DD` date in UTC.
+fn format_date(timestamp: u64) -> String {
+    // Converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_date;
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        // A leap day, and the day after the leap day of a year divisible by 400.
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+        assert_eq!(format_date(951_868_800), "2000-03-01");
+        // The last second of a year and the first one of the next.
+        assert_eq!(format_date(946_684_799), "1999-12-31");
+        assert_eq!(format_date(946_684_800), "2000-01-01");
+        assert_eq!(format_date(1_703_980_800 + 86_399), "2023-12-31");
+        assert_eq!(format_date(1_703_980_800 + 86_400), "2024-01-01");
+    }
+}
@@ -5,7 +5,16 @@ use bevy::{
     prelude::*,
 };
 
-use crate::{player::Player, shop::PointsSpent, GameState};
+use crate::{
+    actions::{Action, ActionState, InputBindings},
+    gameplay_running,
+    pickups::Coins,
+    player::Player,
+    seed::WorldRng,
+    settings::Settings,
+    spawning::SkippedSpawns,
+    DespawnOnRunEnd, GameState,
+};
 
 #[derive(Resource, Default)]
 pub struct Score {
@@ -13,7 +22,16 @@ pub struct Score {
     pub current: ScoreData,
     pub to_be_added: ScoreData,
 }
-#[derive(Default)]
+impl Score {
+    /// The score including the points that are still being counted up.
+    pub fn final_score(&self) -> ScoreData {
+        ScoreData {
+            height: self.current.height + self.to_be_added.height,
+            destruction: self.current.destruction + self.to_be_added.destruction,
+        }
+    }
+}
+#[derive(Default, Clone, Copy)]
 pub struct ScoreData {
     pub height: u64,
     pub destruction: u64,
@@ -39,11 +57,15 @@ fn transfer_score(mut score: ResMut<Score>, time: Res<Time>) {
 }
 
 #[derive(Resource, Default)]
-pub struct UiHints(Vec<UiHint>);
+pub struct UiHints {
+    hints: Vec<UiHint>,
+    /// Which of the hints have already been shown this run.
+    activated: [bool; 4],
+}
 
 pub struct UiHint {
     text: Option<String>,
-    icon: &'static str,
+    action: Action,
     duration: Duration,
 }
 
@@ -60,9 +82,7 @@ pub struct HintImage;
 #[derive(Component)]
 pub struct HintContainer;
 #[derive(Component)]
-pub struct PauseMenu;
-#[derive(Component)]
-pub struct PointsSpentText;
+pub struct CoinsText;
 
 pub struct HudPlugin;
 impl Plugin for HudPlugin {
@@ -79,7 +99,7 @@ impl Plugin for HudPlugin {
                     create_hints,
                     transfer_score,
                 )
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(gameplay_running),
             );
     }
 }
@@ -107,6 +127,7 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         ScoreText,
+        DespawnOnRunEnd,
     ));
     commands.spawn((
         TextBundle {
@@ -128,18 +149,22 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
             ..default()
         },
-        PointsSpentText,
+        CoinsText,
+        DespawnOnRunEnd,
     ));
     // Crosshair
-    commands.spawn(ImageBundle {
-        image: UiImage::new(asset_server.load("textures/crosshair.png")),
-        style: Style {
-            position_type: PositionType::Absolute,
-            margin: UiRect::all(Val::Auto),
+    commands.spawn((
+        ImageBundle {
+            image: UiImage::new(asset_server.load("textures/crosshair.png")),
+            style: Style {
+                position_type: PositionType::Absolute,
+                margin: UiRect::all(Val::Auto),
+                ..default()
+            },
             ..default()
         },
-        ..default()
-    });
+        DespawnOnRunEnd,
+    ));
     // Debug info
     commands.spawn((
         TextBundle {
@@ -192,6 +217,38 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
                         color: Color::WHITE,
                     },
                 },
+                TextSection {
+                    value: "\nSeed: ".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/poppins/Poppins-Regular.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                },
+                TextSection {
+                    value: "0".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/poppins/Poppins-Medium.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                },
+                TextSection {
+                    value: "\nSkipped spawns: ".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/poppins/Poppins-Regular.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                },
+                TextSection {
+                    value: "0".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/poppins/Poppins-Medium.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                },
             ]),
             style: Style {
                 position_type: PositionType::Absolute,
@@ -205,6 +262,7 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         InfoText,
+        DespawnOnRunEnd,
     ));
     // Hints
     commands
@@ -222,6 +280,7 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
                 ..default()
             },
             HintContainer,
+            DespawnOnRunEnd,
         ))
         .with_children(|commands| {
             commands.spawn((
@@ -251,80 +310,20 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
                 HintText,
             ));
         });
-    // Pause
-    commands
-        .spawn((
-            NodeBundle {
-                style: Style {
-                    position_type: PositionType::Absolute,
-                    width: Val::Vw(100.0),
-                    height: Val::Vw(100.0),
-                    // align_items: AlignItems::Center,
-                    // align_content: AlignContent::Center,
-                    // justify_content: JustifyContent::Center,
-                    // flex_direction: FlexDirection::Column,
-                    ..default()
-                },
-                background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.75)),
-                visibility: Visibility::Hidden,
-                ..default()
-            },
-            PauseMenu,
-        ))
-        .with_children(|commands| {
-            commands.spawn(TextBundle {
-                text: Text::from_section(
-                    "PAUSE",
-                    TextStyle {
-                        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
-                        font_size: 75.0,
-                        color: Color::WHITE,
-                    },
-                ),
-                style: Style {
-                    margin: UiRect {
-                        bottom: Val::ZERO,
-                        ..UiRect::all(Val::Auto)
-                    },
-                    ..default()
-                },
-                background_color: BackgroundColor(Color::RED),
-                ..default()
-            });
-            commands.spawn(TextBundle {
-                text: Text::from_section(
-                    "Continue",
-                    TextStyle {
-                        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
-                        font_size: 50.0,
-                        color: Color::WHITE,
-                    },
-                ),
-                style: Style {
-                    margin: UiRect {
-                        top: Val::ZERO,
-                        ..UiRect::all(Val::Auto)
-                    },
-                    ..default()
-                },
-                background_color: BackgroundColor(Color::RED),
-                ..default()
-            });
-        });
 }
 
 fn update_score_text(
     score: Res<Score>,
-    points_spent: Res<PointsSpent>,
-    mut texts: Query<&mut Text, (With<ScoreText>, Without<PointsSpentText>)>,
-    mut texts2: Query<(&mut Text, &mut Visibility), (Without<ScoreText>, With<PointsSpentText>)>,
+    coins: Res<Coins>,
+    mut texts: Query<&mut Text, (With<ScoreText>, Without<CoinsText>)>,
+    mut texts2: Query<(&mut Text, &mut Visibility), (Without<ScoreText>, With<CoinsText>)>,
 ) {
     for mut text in texts.iter_mut() {
         text.sections[0].value = score.current.total().to_string();
     }
     for mut text in texts2.iter_mut() {
-        if points_spent.0 > 0 {
-            text.0.sections[0].value = (score.current.total() - points_spent.0).to_string();
+        if coins.collected > 0 {
+            text.0.sections[0].value = format!("{} coins", coins.balance());
             *text.1 = Visibility::Visible;
         } else {
             *text.1 = Visibility::Hidden;
@@ -336,7 +335,9 @@ fn update_info_text(
     player: Query<&Transform, With<Player>>,
     diagnostics: Res<DiagnosticsStore>,
     entities: Query<Entity>,
-    key: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
+    world_rng: Res<WorldRng>,
+    skipped_spawns: Res<SkippedSpawns>,
 ) {
     let fps = diagnostics
         .get(FrameTimeDiagnosticsPlugin::FPS)
@@ -344,7 +345,7 @@ fn update_info_text(
         .flatten();
     let player_transform = player.single();
     for (mut text, mut visibility) in texts.iter_mut() {
-        if key.just_pressed(KeyCode::F3) {
+        if actions.just_pressed(Action::ToggleInfo) {
             if *visibility == Visibility::Hidden {
                 *visibility = Visibility::Inherited;
             } else {
@@ -355,6 +356,8 @@ fn update_info_text(
             text.sections[1].value = fps.map(|v| format!("{v:.0}")).unwrap_or("N/A".to_string());
             text.sections[3].value = entities.iter().len().to_string();
             text.sections[5].value = format!("{:.1}", player_transform.translation.y);
+            text.sections[7].value = world_rng.seed.to_string();
+            text.sections[9].value = skipped_spawns.0.to_string();
         }
     }
 }
@@ -365,12 +368,13 @@ fn update_hints(
     mut hints: ResMut<UiHints>,
     time: Res<Time>,
     asset_server: Res<AssetServer>,
+    bindings: Res<InputBindings>,
 ) {
-    if let Some(hint) = hints.0.first_mut() {
+    if let Some(hint) = hints.hints.first_mut() {
         if let Some(new_durtation) = hint.duration.checked_sub(time.delta()) {
             hint.duration = new_durtation;
         } else {
-            hints.0.remove(0);
+            hints.hints.remove(0);
             if let Ok(mut visiblity) = visiblity.get_single_mut() {
                 *visiblity = Visibility::Hidden;
             }
@@ -384,7 +388,10 @@ fn update_hints(
                 text.sections[0].value = hint_text;
             }
             if let Ok(mut image) = image.get_single_mut() {
-                image.texture = asset_server.load(hint.icon);
+                image.texture = bindings
+                    .icon(hint.action)
+                    .map(|icon| asset_server.load(icon))
+                    .unwrap_or_default();
             }
         }
     }
@@ -393,33 +400,47 @@ fn update_hints(
 fn create_hints(
     mut hints: ResMut<UiHints>,
     player: Query<&Transform, With<Player>>,
-    mut hints_activated: Local<[bool; 3]>,
+    settings: Res<Settings>,
 ) {
+    if !settings.hints {
+        return;
+    }
     if let Ok(player) = player.get_single() {
         // trace!("{:.1}", player.translation.y);
-        if player.translation.y > 0.6 && !hints_activated[0] {
-            hints.0.push(UiHint {
+        if player.translation.y > 0.6 && !hints.activated[0] {
+            hints.hints.push(UiHint {
                 text: Some("to use your grappling hook on an object.".to_string()),
-                icon: "textures/keyboardmouse/Mouse_Left_Key_Dark.png",
+                action: Action::Hook,
+                duration: Duration::from_secs(5),
+            });
+            hints.activated[0] = true;
+        }
+        if player.translation.y > 50.0 && !hints.activated[3] {
+            hints.hints.push(UiHint {
+                text: Some(
+                    "to switch to a rope you can swing on. Scroll to reel it in or out."
+                        .to_string(),
+                ),
+                action: Action::SwitchHookMode,
                 duration: Duration::from_secs(5),
             });
-            hints_activated[0] = true;
+            hints.activated[3] = true;
         }
-        if player.translation.y > 100.0 && !hints_activated[1] {
-            hints.0.push(UiHint {
+        if player.translation.y > 100.0 && !hints.activated[1] {
+            hints.hints.push(UiHint {
                 text: Some("to dash. (Replinishes upon hooking to an object)".to_string()),
-                icon: "textures/keyboardmouse/Mouse_Right_Key_Dark.png",
+                action: Action::Dash,
                 duration: Duration::from_secs(5),
             });
-            hints_activated[1] = true;
+            hints.activated[1] = true;
         }
-        if player.translation.y > 300.0 && !hints_activated[2] {
-            hints.0.push(UiHint {
+        if player.translation.y > 300.0 && !hints.activated[2] {
+            hints.hints.push(UiHint {
                 text: Some("to toggle additional info.".to_string()),
-                icon: "textures/keyboardmouse/F3_Key_Dark.png",
+                action: Action::ToggleInfo,
                 duration: Duration::from_secs(5),
             });
-            hints_activated[2] = true;
+            hints.activated[2] = true;
         }
     }
 }
@@ -0,0 +1,342 @@
+use bevy::{
+    core_pipeline::core_3d::Transparent3d,
+    ecs::{
+        query::QueryItem,
+        system::{lifetimeless::*, SystemParamItem},
+    },
+    pbr::{
+        MaterialPipeline, MeshPipeline, MeshPipelineKey, RenderMaterials, RenderMeshInstances,
+        SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        view::ExtractedView,
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::materials::OutlineToonMaterial;
+
+/// This plugin draws the [`CubeInstances`] of an entity in a single draw call, instead of one per cube.
+pub struct InstancingPlugin;
+impl Plugin for InstancingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<CubeInstances>::default());
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawCubeInstances>()
+            .init_resource::<SpecializedMeshPipelines<CubeInstancesPipeline>>()
+            .init_resource::<CubeInstanceBuffers>()
+            .add_systems(
+                Render,
+                (
+                    queue_cube_instances.in_set(RenderSet::QueueMeshes),
+                    prepare_cube_instance_buffers.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<CubeInstancesPipeline>();
+    }
+}
+
+/// The cubes drawn by an entity, in world space.
+///
+/// The entity needs a cube mesh and visibility for the cubes to be drawn,
+/// its own transform is only used to sort it among the other transparent objects.
+#[derive(Component, Clone)]
+pub struct CubeInstances {
+    /// The material shared by all cubes, its color gets multiplied with the color of each cube.
+    pub material: Handle<OutlineToonMaterial>,
+    pub instances: Vec<CubeInstance>,
+}
+impl ExtractComponent for CubeInstances {
+    type Query = &'static CubeInstances;
+    type Filter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::Query>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct CubeInstance {
+    pub position: Vec3,
+    pub scale: f32,
+    pub rotation: Quat,
+    /// The color of the cube, its alpha can be used to fade it out.
+    pub color: Vec4,
+}
+impl CubeInstance {
+    pub fn new(transform: &Transform, color: Color) -> Self {
+        Self {
+            position: transform.translation,
+            scale: transform.scale.x,
+            rotation: transform.rotation,
+            color: color.into(),
+        }
+    }
+}
+
+struct CubeInstanceBuffer {
+    buffer: Buffer,
+    /// How many instances fit into the buffer.
+    capacity: usize,
+    length: usize,
+}
+
+/// The instance buffers of every entity with [`CubeInstances`].
+///
+/// Render world entities don't last between frames, so the buffers are kept here instead
+/// and only get recreated when the cubes no longer fit.
+#[derive(Resource, Default)]
+struct CubeInstanceBuffers(HashMap<Entity, CubeInstanceBuffer>);
+
+fn prepare_cube_instance_buffers(
+    query: Query<(Entity, &CubeInstances)>,
+    mut buffers: ResMut<CubeInstanceBuffers>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffers.0.retain(|entity, _| {
+        query
+            .get(*entity)
+            .is_ok_and(|(_, cubes)| !cubes.instances.is_empty())
+    });
+    for (entity, cubes) in query.iter() {
+        // Empty vertex buffers can't be bound, those entities don't get queued.
+        if cubes.instances.is_empty() {
+            continue;
+        }
+        let contents = bytemuck::cast_slice(cubes.instances.as_slice());
+        match buffers.0.get_mut(&entity) {
+            Some(buffer) if buffer.capacity >= cubes.instances.len() => {
+                render_queue.write_buffer(&buffer.buffer, 0, contents);
+                buffer.length = cubes.instances.len();
+            }
+            _ => {
+                let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("cube instance buffer"),
+                    contents,
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                });
+                buffers.0.insert(
+                    entity,
+                    CubeInstanceBuffer {
+                        buffer,
+                        capacity: cubes.instances.len(),
+                        length: cubes.instances.len(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_cube_instances(
+    transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    cube_instances_pipeline: Res<CubeInstancesPipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<CubeInstancesPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    cubes: Query<(Entity, &CubeInstances)>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+) {
+    let draw_cube_instances = transparent_3d_draw_functions
+        .read()
+        .id::<DrawCubeInstances>();
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view, mut transparent_phase) in views.iter_mut() {
+        let view_key =
+            msaa_key | MeshPipelineKey::from_hdr(view.hdr) | MeshPipelineKey::BLEND_ALPHA;
+        let rangefinder = view.rangefinder3d();
+        for (entity, cubes) in cubes.iter() {
+            if cubes.instances.is_empty() {
+                continue;
+            }
+            let Some(mesh_instance) = render_mesh_instances.get(&entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let pipeline = match pipelines.specialize(
+                &pipeline_cache,
+                &cube_instances_pipeline,
+                key,
+                &mesh.layout,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(error) => {
+                    error!("Failed to specialize the cube instances pipeline: {error}");
+                    continue;
+                }
+            };
+            transparent_phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function: draw_cube_instances,
+                distance: rangefinder
+                    .distance_translation(&mesh_instance.transforms.transform.translation),
+                batch_range: 0..1,
+                dynamic_offset: None,
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+struct CubeInstancesPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    material_layout: BindGroupLayout,
+}
+impl FromWorld for CubeInstancesPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let material_pipeline = world.resource::<MaterialPipeline<OutlineToonMaterial>>();
+        Self {
+            shader: world
+                .resource::<AssetServer>()
+                .load("shaders/instanced_outline_toon.wgsl"),
+            mesh_pipeline: material_pipeline.mesh_pipeline.clone(),
+            material_layout: material_pipeline.material_layout.clone(),
+        }
+    }
+}
+impl SpecializedMeshPipeline for CubeInstancesPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        // The material goes between the view and the mesh, just like with a regular `Material`.
+        descriptor.layout.insert(1, self.material_layout.clone());
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<CubeInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            // The mesh itself uses locations 0 through 5.
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 6,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size(),
+                    shader_location: 7,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size() * 2,
+                    shader_location: 8,
+                },
+            ],
+        });
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+        Ok(descriptor)
+    }
+}
+
+type DrawCubeInstances = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetCubeMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawMeshInstanced,
+);
+
+struct SetCubeMaterialBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetCubeMaterialBindGroup<I> {
+    type Param = SRes<RenderMaterials<OutlineToonMaterial>>;
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<CubeInstances>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        cubes: &'w CubeInstances,
+        materials: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(material) = materials.into_inner().get(&cubes.material.id()) else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, &material.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawMeshInstanced;
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<CubeInstanceBuffers>,
+    );
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _cubes: (),
+        (meshes, render_mesh_instances, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(instance_buffer) = instance_buffers.into_inner().0.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(mesh_instance) = render_mesh_instances.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+        let instances = 0..instance_buffer.length as u32;
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
@@ -1,16 +1,34 @@
 #![allow(clippy::type_complexity)]
 
-use std::default;
-
+use crate::actions::ActionsPlugin;
 use crate::menu::MenuPlugin;
 use crate::player::PlayerPlugin;
 
+mod actions;
+mod biomes;
+mod chunks;
+mod game_over;
+mod gumball_kinds;
+mod gumball_pool;
+mod hazards;
+mod high_scores;
 mod hud;
+mod instancing;
 mod materials;
 mod menu;
+mod pause;
+mod pickups;
+mod platforms;
 mod player;
+mod seed;
+mod settings;
 mod shop;
+mod spatial_hash;
+mod spawn_table;
 mod spawning;
+mod storage;
+mod structure;
+mod touch;
 
 use bevy::app::App;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
@@ -19,12 +37,28 @@ use bevy_atmosphere::collection::gradient::Gradient;
 use bevy_atmosphere::model::AtmosphereModel;
 use bevy_atmosphere::plugin::AtmospherePlugin;
 use bevy_toon_shader::ToonShaderPlugin;
-use bevy_xpbd_3d::plugins::{PhysicsDebugPlugin, PhysicsPlugins};
+use bevy_xpbd_3d::plugins::{
+    setup::{Physics, PhysicsTime, TimestepMode},
+    PhysicsDebugPlugin, PhysicsPlugins,
+};
 use bevy_xpbd_3d::resources::Gravity;
-use hud::HudPlugin;
+use biomes::BiomePlugin;
+use chunks::{ChunkPlugin, WorldChunks};
+use game_over::{GameOverPlugin, RunStats};
+use gumball_pool::GumballPoolPlugin;
+use hazards::HazardPlugin;
+use high_scores::HighScoresPlugin;
+use hud::{HudPlugin, Score, UiHints};
 use materials::CustomMaterialsPlugin;
-use shop::ShopPlugin;
-use spawning::SpawnPlugin;
+use pause::PausePlugin;
+use pickups::{Coins, PickupPlugin};
+use platforms::PlatformPlugin;
+use seed::SeedPlugin;
+use settings::SettingsPlugin;
+use shop::{IsShopping, ShopPlugin};
+use spatial_hash::SpatialHashPlugin;
+use spawning::{SkippedSpawns, SpawnPlugin, SpawnTimers};
+use touch::TouchPlugin;
 
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
@@ -36,6 +70,64 @@ enum GameState {
     // Here the menu is drawn and waiting for player interaction
     #[default]
     Menu,
+    // Passed through for a single frame to start a fresh run from `Playing`
+    Restarting,
+    // The run is over and its summary is shown
+    GameOver,
+}
+
+// Whether the game is paused, only relevant during `GameState::Playing`
+#[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Run condition for gameplay systems, which only run while playing and not paused.
+fn gameplay_running(
+    game_state: Res<State<GameState>>,
+    pause_state: Res<State<PauseState>>,
+) -> bool {
+    *game_state.get() == GameState::Playing && *pause_state.get() == PauseState::Running
+}
+
+/// Marks an entity as part of a run, so it gets despawned once the run ends.
+/// The run ends when returning to the menu or restarting, which keeps the world visible behind the game over screen
+#[derive(Component)]
+pub struct DespawnOnRunEnd;
+
+fn despawn_run_entities(mut commands: Commands, entities: Query<Entity, With<DespawnOnRunEnd>>) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Resets everything that tracks the progress of a run, so every run starts from a clean slate.
+fn reset_run_resources(mut commands: Commands) {
+    commands.insert_resource(Score::default());
+    commands.insert_resource(UiHints::default());
+    commands.insert_resource(Coins::default());
+    commands.insert_resource(IsShopping::default());
+    commands.insert_resource(SpawnTimers::default());
+    commands.insert_resource(SkippedSpawns::default());
+    commands.insert_resource(WorldChunks::default());
+    commands.insert_resource(RunStats::default());
+}
+
+/// Lets the physics run several steps on slow frames to catch up, like Bevy's own fixed timestep,
+/// instead of the whole game slowing down below 60 FPS.
+fn catch_up_physics(mut physics_time: ResMut<Time<Physics>>) {
+    if let TimestepMode::Fixed {
+        max_delta_overstep, ..
+    } = physics_time.timestep_mode_mut()
+    {
+        *max_delta_overstep = Time::<Virtual>::default().max_delta();
+    }
+}
+
+fn restart(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
 }
 
 pub struct GamePlugin;
@@ -43,6 +135,14 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
+            .add_state::<PauseState>()
+            .add_systems(Startup, catch_up_physics)
+            .add_systems(OnEnter(GameState::Playing), reset_run_resources)
+            .add_systems(OnEnter(GameState::Menu), despawn_run_entities)
+            .add_systems(
+                OnEnter(GameState::Restarting),
+                (despawn_run_entities, restart),
+            )
             .insert_resource(Msaa::Off)
             .insert_resource(ClearColor(Color::rgb(0.6, 0.8, 0.9)))
             .insert_resource(AmbientLight {
@@ -67,6 +167,22 @@ impl Plugin for GamePlugin {
                 FrameTimeDiagnosticsPlugin,
                 CustomMaterialsPlugin,
                 ShopPlugin,
+            ))
+            .add_plugins((
+                ActionsPlugin,
+                PausePlugin,
+                SettingsPlugin,
+                TouchPlugin,
+                GameOverPlugin,
+                HighScoresPlugin,
+                SeedPlugin,
+                BiomePlugin,
+                ChunkPlugin,
+                SpatialHashPlugin,
+                GumballPoolPlugin,
+                PlatformPlugin,
+                HazardPlugin,
+                PickupPlugin,
             ));
     }
 }
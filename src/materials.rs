@@ -4,10 +4,13 @@ use bevy::{
 };
 use bevy_toon_shader::{ToonShaderMainCamera, ToonShaderSun};
 
+use crate::instancing::InstancingPlugin;
+
 pub struct CustomMaterialsPlugin;
 impl Plugin for CustomMaterialsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<OutlineToonMaterial>::default())
+            .add_plugins(InstancingPlugin)
             .add_plugins(UiMaterialPlugin::<RoundedRectangleMaterial>::default())
             .add_systems(Update, update_outline_toon_shader);
     }
@@ -1,3 +1,5 @@
+use crate::high_scores::{spawn_high_score_table, HighScores};
+use crate::seed::{daily_seed, SeedSource};
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_xpbd_3d::prelude::{Physics, PhysicsTime};
@@ -8,15 +10,18 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(
+                Update,
+                (click_play_button, type_seed, update_seed_text).run_if(in_state(GameState::Menu)),
+            )
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
 
 #[derive(Component)]
-struct ButtonColors {
-    normal: Color,
-    hovered: Color,
+pub struct ButtonColors {
+    pub normal: Color,
+    pub hovered: Color,
 }
 
 impl Default for ButtonColors {
@@ -31,7 +36,11 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 
-fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    high_scores: Res<HighScores>,
+) {
     debug!("Opening menu");
     commands.spawn((Camera2dBundle::default(), Menu));
     commands
@@ -89,6 +98,81 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ));
                 });
         });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(110.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|children| {
+            let style = TextStyle {
+                font_size: 20.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            };
+            children.spawn((TextBundle::from_section("", style.clone()), SeedText));
+            for (button, text) in [
+                (SeedButton::Random, "Random"),
+                (SeedButton::Daily, "Daily"),
+                (SeedButton::Custom, "Custom"),
+            ] {
+                let button_colors = ButtonColors::default();
+                children
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(90.0),
+                                height: Val::Px(30.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        },
+                        button_colors,
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(text, style.clone()));
+                    });
+            }
+        });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(20.0),
+                    right: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|children| {
+            spawn_high_score_table(
+                children,
+                &high_scores,
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            );
+        });
     commands
         .spawn((
             NodeBundle {
@@ -190,6 +274,18 @@ struct ChangeState(GameState);
 #[derive(Component)]
 struct OpenLink(&'static str);
 
+/// Changes the [`SeedSource`] of the next run.
+#[derive(Component, Clone, Copy)]
+enum SeedButton {
+    Random,
+    Daily,
+    /// Lets you type in a seed.
+    Custom,
+}
+
+#[derive(Component)]
+struct SeedText;
+
 fn click_play_button(
     mut next_state: ResMut<NextState<GameState>>,
     mut interaction_query: Query<
@@ -199,15 +295,28 @@ fn click_play_button(
             &ButtonColors,
             Option<&ChangeState>,
             Option<&OpenLink>,
+            Option<&SeedButton>,
         ),
         (Changed<Interaction>, With<Button>),
     >,
+    mut seed_source: ResMut<SeedSource>,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (interaction, mut color, button_colors, change_state, open_link, seed_button) in
+        &mut interaction_query
+    {
         match *interaction {
             Interaction::Pressed => {
                 if let Some(state) = change_state {
                     next_state.set(state.0.clone());
+                } else if let Some(button) = seed_button {
+                    *seed_source = match button {
+                        SeedButton::Random => SeedSource::Random,
+                        SeedButton::Daily => SeedSource::Daily,
+                        SeedButton::Custom => match *seed_source {
+                            SeedSource::Fixed(seed) => SeedSource::Fixed(seed),
+                            _ => SeedSource::Fixed(0),
+                        },
+                    };
                 } else if let Some(link) = open_link {
                     if let Err(error) = webbrowser::open(link.0) {
                         warn!("Failed to open link {error:?}");
@@ -224,6 +333,43 @@ fn click_play_button(
     }
 }
 
+/// Typing digits edits the seed while a custom seed is selected.
+fn type_seed(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut seed_source: ResMut<SeedSource>,
+) {
+    let SeedSource::Fixed(seed) = *seed_source else {
+        characters.clear();
+        return;
+    };
+    let mut new_seed = seed;
+    for character in characters.read() {
+        if let Some(digit) = character.char.to_digit(10) {
+            new_seed = new_seed
+                .checked_mul(10)
+                .and_then(|seed| seed.checked_add(digit as u64))
+                .unwrap_or(new_seed);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        new_seed /= 10;
+    }
+    if new_seed != seed {
+        *seed_source = SeedSource::Fixed(new_seed);
+    }
+}
+
+fn update_seed_text(seed_source: Res<SeedSource>, mut texts: Query<&mut Text, With<SeedText>>) {
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = match *seed_source {
+            SeedSource::Random => "Seed: Random".to_string(),
+            SeedSource::Daily => format!("Seed: Daily ({})", daily_seed()),
+            SeedSource::Fixed(seed) => format!("Seed: {seed}"),
+        };
+    }
+}
+
 fn cleanup_menu(
     mut commands: Commands,
     menu: Query<Entity, With<Menu>>,
@@ -0,0 +1,365 @@
+use bevy::{prelude::*, window::CursorGrabMode};
+use bevy_xpbd_3d::prelude::{Physics, PhysicsTime};
+
+use crate::{
+    actions::{Action, ActionState},
+    menu::ButtonColors,
+    settings::Settings,
+    touch::TouchControls,
+    DespawnOnRunEnd, GameState, PauseState,
+};
+
+pub struct PausePlugin;
+
+/// This plugin handles pausing the game and the pause menu
+/// Everything that uses the `gameplay_running` run condition is frozen while paused
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), setup_pause_menu)
+            .add_systems(OnExit(GameState::Playing), reset_pause)
+            .add_systems(OnEnter(PauseState::Paused), pause)
+            .add_systems(OnExit(PauseState::Paused), unpause)
+            .add_systems(
+                Update,
+                (
+                    toggle_pause.run_if(in_state(GameState::Playing)),
+                    (click_pause_buttons, update_settings_text)
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(in_state(PauseState::Paused)),
+                ),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct PauseMenu;
+/// The panel with the main pause menu buttons.
+#[derive(Component)]
+pub struct PausePanel;
+/// The panel with the settings, shown instead of the [`PausePanel`].
+#[derive(Component)]
+pub struct SettingsPanel;
+/// The text showing the value of a setting.
+#[derive(Component, Clone, Copy)]
+pub enum SettingText {
+    Fov,
+    Hints,
+    Sensitivity,
+    InvertY,
+}
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Continue,
+    Restart,
+    Settings,
+    QuitToMenu,
+    Back,
+    FovDown,
+    FovUp,
+    ToggleHints,
+    SensitivityDown,
+    SensitivityUp,
+    ToggleInvertY,
+}
+
+/// Locks and hides the cursor while playing, unless playing with touch controls.
+pub fn grab_cursor(window: &mut Window, touch: &TouchControls) {
+    // There's no cursor to grab on a touchscreen.
+    if !touch.active {
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+        window.cursor.visible = false;
+    }
+}
+pub fn release_cursor(window: &mut Window) {
+    window.cursor.grab_mode = CursorGrabMode::None;
+    window.cursor.visible = true;
+}
+
+fn setup_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let title_style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 75.0,
+        color: Color::WHITE,
+    };
+    let button_style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+    let panel = Style {
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        row_gap: Val::Px(10.0),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.75)),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            PauseMenu,
+            DespawnOnRunEnd,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section("PAUSE", title_style.clone()));
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: panel.clone(),
+                        ..default()
+                    },
+                    PausePanel,
+                ))
+                .with_children(|commands| {
+                    for (button, text) in [
+                        (PauseButton::Continue, "Continue"),
+                        (PauseButton::Restart, "Restart"),
+                        (PauseButton::Settings, "Settings"),
+                        (PauseButton::QuitToMenu, "Quit to Menu"),
+                    ] {
+                        let text = TextBundle::from_section(text, button_style.clone());
+                        spawn_button(commands, button, text);
+                    }
+                });
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            ..panel
+                        },
+                        ..default()
+                    },
+                    SettingsPanel,
+                ))
+                .with_children(|commands| {
+                    let setting_text = |name, setting| {
+                        (
+                            TextBundle::from_sections([
+                                TextSection::new(name, button_style.clone()),
+                                TextSection::new("?", button_style.clone()),
+                            ]),
+                            setting,
+                        )
+                    };
+                    for (down, name, setting, up) in [
+                        (
+                            PauseButton::FovDown,
+                            "Field of View ",
+                            SettingText::Fov,
+                            PauseButton::FovUp,
+                        ),
+                        (
+                            PauseButton::SensitivityDown,
+                            "Sensitivity ",
+                            SettingText::Sensitivity,
+                            PauseButton::SensitivityUp,
+                        ),
+                    ] {
+                        commands
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    align_items: AlignItems::Center,
+                                    column_gap: Val::Px(10.0),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|commands| {
+                                let text = TextBundle::from_section("-", button_style.clone());
+                                spawn_button(commands, down, text);
+                                commands.spawn(setting_text(name, setting));
+                                let text = TextBundle::from_section("+", button_style.clone());
+                                spawn_button(commands, up, text);
+                            });
+                    }
+                    for (button, name, setting) in [
+                        (PauseButton::ToggleHints, "Hints ", SettingText::Hints),
+                        (
+                            PauseButton::ToggleInvertY,
+                            "Invert Y ",
+                            SettingText::InvertY,
+                        ),
+                    ] {
+                        spawn_button(commands, button, setting_text(name, setting));
+                    }
+                    let text = TextBundle::from_section("Back", button_style.clone());
+                    spawn_button(commands, PauseButton::Back, text);
+                });
+        });
+}
+
+fn spawn_button(commands: &mut ChildBuilder, button: PauseButton, text: impl Bundle) {
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(280.0),
+                    height: Val::Px(55.0),
+                    padding: UiRect::horizontal(Val::Px(15.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: ButtonColors::default().normal.into(),
+                ..default()
+            },
+            ButtonColors::default(),
+            button,
+        ))
+        .with_children(|commands| {
+            commands.spawn(text);
+        });
+}
+
+fn toggle_pause(
+    actions: Res<ActionState>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        next_pause_state.set(match pause_state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
+}
+
+fn pause(
+    mut window: Query<&mut Window>,
+    mut pause_menu: Query<&mut Visibility, With<PauseMenu>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if let Ok(mut window) = window.get_single_mut() {
+        release_cursor(&mut window);
+    }
+    for mut visibility in pause_menu.iter_mut() {
+        *visibility = Visibility::Visible;
+    }
+    time.pause();
+    physics_time.pause();
+}
+
+fn unpause(
+    game_state: Res<State<GameState>>,
+    touch: Res<TouchControls>,
+    mut window: Query<&mut Window>,
+    mut pause_menu: Query<&mut Visibility, With<PauseMenu>>,
+    mut panels: Query<
+        (&mut Style, Has<SettingsPanel>),
+        Or<(With<PausePanel>, With<SettingsPanel>)>,
+    >,
+    mut time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if *game_state.get() == GameState::Playing {
+        if let Ok(mut window) = window.get_single_mut() {
+            grab_cursor(&mut window, &touch);
+        }
+    }
+    for mut visibility in pause_menu.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    for (mut style, is_settings) in panels.iter_mut() {
+        style.display = if is_settings {
+            Display::None
+        } else {
+            Display::Flex
+        };
+    }
+    time.unpause();
+    physics_time.unpause();
+}
+
+/// Leaving `Playing` while paused shouldn't leave the next run paused.
+fn reset_pause(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+fn click_pause_buttons(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut settings: ResMut<Settings>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &PauseButton,
+        ),
+        Changed<Interaction>,
+    >,
+    mut panels: Query<
+        (&mut Style, Has<SettingsPanel>),
+        Or<(With<PausePanel>, With<SettingsPanel>)>,
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                PauseButton::Continue => next_pause_state.set(PauseState::Running),
+                PauseButton::Restart => next_state.set(GameState::Restarting),
+                PauseButton::QuitToMenu => next_state.set(GameState::Menu),
+                PauseButton::Settings | PauseButton::Back => {
+                    let show_settings = matches!(button, PauseButton::Settings);
+                    for (mut style, is_settings) in panels.iter_mut() {
+                        style.display = if is_settings == show_settings {
+                            Display::Flex
+                        } else {
+                            Display::None
+                        };
+                    }
+                }
+                PauseButton::FovDown => {
+                    settings.fov = (settings.fov - 5.0).max(Settings::MIN_FOV);
+                }
+                PauseButton::FovUp => {
+                    settings.fov = (settings.fov + 5.0).min(Settings::MAX_FOV);
+                }
+                PauseButton::ToggleHints => settings.hints = !settings.hints,
+                PauseButton::SensitivityDown => {
+                    settings.sensitivity =
+                        (settings.sensitivity - 0.25).max(Settings::MIN_SENSITIVITY);
+                }
+                PauseButton::SensitivityUp => {
+                    settings.sensitivity =
+                        (settings.sensitivity + 0.25).min(Settings::MAX_SENSITIVITY);
+                }
+                PauseButton::ToggleInvertY => settings.invert_y = !settings.invert_y,
+            },
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn update_settings_text(settings: Res<Settings>, mut texts: Query<(&mut Text, &SettingText)>) {
+    let on_off = |on| if on { "On" } else { "Off" }.to_string();
+    for (mut text, setting) in texts.iter_mut() {
+        text.sections[1].value = match setting {
+            SettingText::Fov => format!("{:.0}", settings.fov),
+            SettingText::Hints => on_off(settings.hints),
+            SettingText::Sensitivity => format!("{:.2}", settings.sensitivity),
+            SettingText::InvertY => on_off(settings.invert_y),
+        };
+    }
+}
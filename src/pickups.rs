@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{
+    gameplay_running, materials::OutlineToonMaterial, player::Player, spatial_hash::SpatialHash,
+    spawn_table::Archetype,
+};
+
+/// Pickups closer to the player than this fly towards them.
+pub const MAGNET_RADIUS: f32 = 12.0;
+/// How fast pickups fly towards the player.
+pub const MAGNET_SPEED: f32 = 40.0;
+/// Pickups closer to the player than this get collected.
+pub const COLLECT_RADIUS: f32 = 1.5;
+/// How many coins a candy coin is worth.
+pub const CANDY_COIN_VALUE: u64 = 1;
+/// How many coins a golden gumball is worth.
+pub const GOLDEN_GUMBALL_VALUE: u64 = 25;
+
+/// The currency collected from pickups during a run, spent in the shop.
+///
+/// It's separate from the score, so buying upgrades doesn't lower it.
+#[derive(Resource, Default)]
+pub struct Coins {
+    pub collected: u64,
+    pub spent: u64,
+}
+impl Coins {
+    /// The coins which can still be spent.
+    pub fn balance(&self) -> u64 {
+        self.collected - self.spent
+    }
+}
+
+/// Something floating in the air which gets collected for coins by flying into it.
+#[derive(Component)]
+pub struct Pickup {
+    /// How many coins it's worth.
+    pub value: u64,
+}
+
+/// The materials shared by all pickups.
+#[derive(Resource)]
+pub struct PickupMaterials {
+    pub candy_coin: Handle<OutlineToonMaterial>,
+    pub golden_gumball: Handle<OutlineToonMaterial>,
+}
+impl FromWorld for PickupMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut toon_materials = world.resource_mut::<Assets<OutlineToonMaterial>>();
+        Self {
+            candy_coin: toon_materials.add(OutlineToonMaterial {
+                color: Color::rgb_linear(4.0, 1.0, 2.0),
+                outline_color: Color::rgb_linear(8.0, 8.0, 8.0),
+                ..default()
+            }),
+            golden_gumball: toon_materials.add(OutlineToonMaterial {
+                color: Color::rgb_linear(8.0, 5.0, 0.5),
+                outline_color: Color::rgb_linear(16.0, 12.0, 2.0),
+                ..default()
+            }),
+        }
+    }
+}
+
+pub struct PickupPlugin;
+
+/// This plugin pulls pickups near the player towards them and collects them.
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Coins>()
+            .init_resource::<PickupMaterials>()
+            .add_systems(Update, collect_pickups.run_if(gameplay_running));
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    player: Query<&Transform, With<Player>>,
+    mut pickups: Query<(&Pickup, &mut Transform), Without<Player>>,
+    spatial_hash: Res<SpatialHash>,
+    mut coins: ResMut<Coins>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+    let target = player_transform.translation;
+    for archetype in [Archetype::CandyCoin, Archetype::GoldenGumball] {
+        for (entity, _) in spatial_hash.within(archetype, target, MAGNET_RADIUS) {
+            let Ok((pickup, mut transform)) = pickups.get_mut(entity) else {
+                continue;
+            };
+            let offset = target - transform.translation;
+            let distance = offset.length();
+            if distance < COLLECT_RADIUS {
+                coins.collected += pickup.value;
+                commands.entity(entity).despawn();
+                continue;
+            }
+            transform.translation +=
+                offset / distance * (MAGNET_SPEED * time.delta_seconds()).min(distance);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSchedule, PhysicsStepSet};
+use serde::Deserialize;
+
+use crate::gameplay_running;
+
+/// How fast platforms are pulled back onto their motion when they drift off it, per second.
+pub const DRIFT_CORRECTION: f32 = 5.0;
+/// The time step used to work out how fast a platform moves along its motion.
+const VELOCITY_STEP: f32 = 0.01;
+
+/// How a [`Platform`] moves, set by the `motion` of a spawn table entry.
+///
+/// Platforms are kinematic, they move with a velocity instead of being teleported,
+/// so whatever stands on them or hangs from them gets carried along.
+#[derive(Deserialize, Clone, Default, Debug)]
+pub enum PlatformMotion {
+    /// Stays where it spawned.
+    #[default]
+    Still,
+    /// Moves from where it spawned through `points`, relative to that spot, and back again.
+    Path { points: Vec<Vec3>, speed: f32 },
+    /// Circles around where it spawned, taking `period` seconds per lap.
+    Orbit {
+        radius: f32,
+        period: f32,
+        #[serde(default = "up")]
+        axis: Vec3,
+    },
+    /// Spins in place around `axis`, taking `period` seconds per turn.
+    Spin {
+        period: f32,
+        #[serde(default = "up")]
+        axis: Vec3,
+    },
+}
+impl PlatformMotion {
+    /// Where a platform is `time` seconds into its motion, relative to where it spawned.
+    fn offset(&self, time: f32) -> Vec3 {
+        match self {
+            Self::Still | Self::Spin { .. } => Vec3::ZERO,
+            Self::Path { points, speed } => {
+                let mut previous = Vec3::ZERO;
+                let length: f32 = points
+                    .iter()
+                    .map(|point| {
+                        let distance = previous.distance(*point);
+                        previous = *point;
+                        distance
+                    })
+                    .sum();
+                if length <= 0.0 {
+                    return Vec3::ZERO;
+                }
+                // Going back is the same as going forward from the other end.
+                let mut distance = (time * speed).rem_euclid(length * 2.0);
+                if distance > length {
+                    distance = length * 2.0 - distance;
+                }
+                let mut start = Vec3::ZERO;
+                for point in points {
+                    let segment = start.distance(*point);
+                    // Repeated points make segments without a length, which are skipped over.
+                    if segment > 0.0 && distance <= segment {
+                        return start.lerp(*point, distance / segment);
+                    }
+                    distance -= segment;
+                    start = *point;
+                }
+                start
+            }
+            Self::Orbit {
+                radius,
+                period,
+                axis,
+            } => {
+                // A lap taking no time can't be moved along, so it's treated as standing still.
+                if *period == 0.0 {
+                    return Vec3::ZERO;
+                }
+                // A zero axis can't be turned around, so it falls back to the default one.
+                let axis = axis.try_normalize().unwrap_or_else(up);
+                let angle = time / period * TAU;
+                Quat::from_axis_angle(axis, angle) * axis.any_orthonormal_vector() * *radius
+            }
+        }
+    }
+    fn angular_velocity(&self) -> Vec3 {
+        match self {
+            Self::Spin { period, axis } if *period != 0.0 => {
+                axis.try_normalize().unwrap_or_else(up) * TAU / *period
+            }
+            _ => Vec3::ZERO,
+        }
+    }
+}
+
+fn up() -> Vec3 {
+    Vec3::Y
+}
+
+/// A kinematic object moving along its [`PlatformMotion`].
+#[derive(Component)]
+pub struct Platform {
+    pub motion: PlatformMotion,
+    /// The spot the platform spawned at, its motion is relative to it.
+    pub origin: Vec3,
+    /// How long the platform has been moving, in seconds.
+    pub time: f32,
+}
+impl Platform {
+    /// Where the platform should be right now.
+    pub fn position(&self) -> Vec3 {
+        self.origin + self.motion.offset(self.time)
+    }
+}
+
+pub struct PlatformPlugin;
+
+/// This plugin moves the platforms.
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        // Platforms only move along with the physics, so they don't drift off while it's paused.
+        app.add_systems(
+            PhysicsSchedule,
+            move_platforms
+                .before(PhysicsStepSet::BroadPhase)
+                .run_if(gameplay_running),
+        );
+    }
+}
+
+pub fn move_platforms(
+    mut platforms: Query<(
+        &mut Platform,
+        &Transform,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+    )>,
+    time: Res<Time<Physics>>,
+) {
+    for (mut platform, transform, mut velocity, mut angular_velocity) in platforms.iter_mut() {
+        platform.time += time.delta_seconds();
+        let motion = &platform.motion;
+        let along = (motion.offset(platform.time + VELOCITY_STEP) - motion.offset(platform.time))
+            / VELOCITY_STEP;
+        let drift = platform.position() - transform.translation;
+        velocity.0 = along + drift * DRIFT_CORRECTION;
+        angular_velocity.0 = motion.angular_velocity();
+    }
+}
@@ -1,62 +1,186 @@
 use instant::Duration;
 
 use crate::{
+    actions::{Action, ActionState},
+    gameplay_running,
+    gumball_kinds::{GumballKind, EXPLOSION_RADIUS, EXPLOSION_STRENGTH},
+    gumball_pool::{GumballPool, PaletteMaterial, HOOK_OUTLINE_COLOR},
+    hazards::{ElectricCooldown, ANVIL_PUSH, ELECTRIC_STUN, SPIKE_KNOCKBACK, SPIKE_STUN},
     hud::Score,
     materials::OutlineToonMaterial,
+    pause::grab_cursor,
+    platforms::move_platforms,
+    settings::Settings,
+    spatial_hash::SpatialHash,
+    spawn_table::Archetype,
     spawning::{OutlineToonFadeOut, Thingajamig},
-    GameState,
+    structure::VoxelPoints,
+    touch::TouchControls,
+    DespawnOnRunEnd, GameState,
 };
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
-    input::mouse::MouseMotion,
     prelude::*,
-    window::CursorGrabMode,
 };
 use bevy_atmosphere::plugin::AtmosphereCamera;
 use bevy_toon_shader::ToonShaderMainCamera;
-use bevy_xpbd_3d::{math::Quaternion, prelude::*};
+use bevy_xpbd_3d::{math::Quaternion, prelude::*, PhysicsSchedule, PhysicsStepSet};
+use serde::{Deserialize, Serialize};
 
 pub const ACCELERATION: f32 = 30.0;
 pub const JUMP_VELOCITY: f32 = 10.0;
-pub const HOOK_SPEED: f32 = 0.75;
+/// How fast the hook speeds the player up while pulling, per second.
+pub const HOOK_ACCELERATION: f32 = 45.0;
+pub const ROPE_REEL_SPEED: f32 = 2.0;
+pub const MIN_ROPE_LENGTH: f32 = 2.0;
 pub const DASH_POWER: f32 = 100.0; // default
                                    // pub const DASH_POWER: f32 = 200.0;
 pub const DASH_COOLDOWN: f64 = 1.0;
+/// How far above the center of the player the camera sits.
+pub const CAMERA_HEIGHT: f32 = 0.75;
+/// How far up or down the camera can look, in radians, short of straight up so it can't flip over.
+pub const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+/// The player has to hit a thingamajig at least this fast to break anything.
+pub const MIN_BREAK_SPEED: f32 = 10.0;
+/// How far from the player voxels break, on top of how fast the player is going.
+pub const BREAK_REACH: f32 = 2.0;
+pub const BREAK_RADIUS_PER_SPEED: f32 = 0.1;
+pub const MAX_BREAK_RADIUS: f32 = 12.0;
+/// How fast broken voxels fly away, relative to the speed of the player.
+pub const KNOCK_STRENGTH: f32 = 0.5;
 
 pub struct PlayerPlugin;
 
 #[derive(Component)]
 pub struct Player {
-    pub hooked_onto: Option<Entity>,
+    pub hooked_onto: Option<HookAnchor>,
+    pub hook_mode: HookMode,
+    pub rope: Option<Rope>,
     pub dash: Option<()>,
     pub upgrades: PlayerUpgrades,
+    /// How many more seconds the player can't use the hook for, after running into a hazard.
+    pub stunned: f32,
 }
+impl Player {
+    /// Breaks the hook and keeps the player from hooking anything for `duration` seconds.
+    pub fn stun(&mut self, duration: f32) {
+        self.hooked_onto = None;
+        self.stunned = self.stunned.max(duration);
+    }
+}
+
+/// The spot the grappling hook is attached to.
+///
+/// The point and normal are stored in the local space of the hooked entity,
+/// so the anchor follows the entity as it moves or rotates.
+#[derive(Clone, Copy, Debug)]
+pub struct HookAnchor {
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+impl HookAnchor {
+    /// Creates an anchor from a world space hit point and normal on the entity with `transform`.
+    pub fn from_world(entity: Entity, transform: &Transform, point: Vec3, normal: Vec3) -> Self {
+        let inverse = transform.compute_affine().inverse();
+        Self {
+            entity,
+            point: inverse.transform_point3(point),
+            normal: (transform.rotation.inverse() * normal).normalize_or_zero(),
+        }
+    }
+    /// The anchor point in world space, given the current transform of the hooked entity.
+    pub fn world_point(&self, transform: &Transform) -> Vec3 {
+        transform.transform_point(self.point)
+    }
+    /// The surface normal at the anchor in world space, given the current transform of the hooked entity.
+    pub fn world_normal(&self, transform: &Transform) -> Vec3 {
+        transform.rotation * self.normal
+    }
+}
+
+/// How the grappling hook behaves once it is attached to something.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HookMode {
+    /// Pulls the player towards the hooked object in a straight line.
+    #[default]
+    Pull,
+    /// Attaches a rope to the hooked object which the player can swing on.
+    Rope,
+}
+
+/// The rope of the grappling hook while in [`HookMode::Rope`].
+pub struct Rope {
+    /// The entity holding the [`DistanceJoint`] between the player and the hooked object.
+    pub joint: Entity,
+    /// The maximum length of the rope, changed by reeling it in or letting it out.
+    pub length: f32,
+}
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct PlayerUpgrades {
     pub hook_range: u64,
     pub hook_strength: u64,
     pub dash_strength: u64,
 }
 
-#[derive(Component)]
-pub struct CameraLook(Transform);
+/// Where the player is looking, shared by the camera and everything aimed with it like the hook.
+#[derive(Component, Default)]
+pub struct CameraLook {
+    /// The rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// The rotation up or down, in radians, within [`MAX_PITCH`].
+    pub pitch: f32,
+}
+impl CameraLook {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch)
+    }
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::NEG_Z
+    }
+    /// Where the camera is, given the transform of the player.
+    pub fn eye(player_transform: &Transform) -> Vec3 {
+        player_transform.translation + Vec3::Y * CAMERA_HEIGHT
+    }
+}
+
+/// Presses handled on the physics timestep, kept until the next physics step so they aren't missed
+/// on frames without one.
+#[derive(Resource, Default)]
+struct QueuedActions {
+    jump: bool,
+    dash: bool,
+}
 
 /// This plugin handles player related stuff like movement
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_player)
+        app.init_resource::<QueuedActions>()
+            .add_systems(OnEnter(GameState::Playing), spawn_player)
             .add_systems(
                 Update,
                 (
-                    player_move,
+                    queue_actions,
                     player_look,
                     player_create_hook,
+                    player_switch_hook_mode,
                     player_use_and_remove_hook,
-                    player_dash,
                     player_update_score,
                     player_colliding_entities,
+                    player_recover_from_stun,
                 )
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(gameplay_running),
+            )
+            // Forces are applied on every physics step, so they don't depend on the frame rate.
+            // They read `Time<Physics>`, as xpbd only sets the generic `Time` for the first step of a frame.
+            .add_systems(
+                PhysicsSchedule,
+                (player_move, player_dash, player_pull_hook)
+                    .chain()
+                    .after(move_platforms)
+                    .before(PhysicsStepSet::BroadPhase)
+                    .run_if(gameplay_running),
             );
     }
 }
@@ -65,6 +189,7 @@ fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<Settings>,
 ) {
     commands
         .spawn((
@@ -78,7 +203,7 @@ fn spawn_player(
                 transform: Transform::from_xyz(0.0, 0.5, 0.0),
                 ..default()
             },
-            CameraLook(Transform::default()),
+            CameraLook::default(),
             RigidBody::Dynamic,
             LockedAxes::ROTATION_LOCKED,
             Collider::capsule(1.0, 0.5),
@@ -87,12 +212,11 @@ fn spawn_player(
             LinearDamping(2.0),
             Player {
                 hooked_onto: None,
+                hook_mode: HookMode::default(),
+                rope: None,
                 dash: Some(()),
-                upgrades: PlayerUpgrades {
-                    hook_range: 0,
-                    hook_strength: 0,
-                    dash_strength: 0,
-                },
+                upgrades: PlayerUpgrades::default(),
+                stunned: 0.0,
             },
             ShapeCaster::new(
                 Collider::cylinder(0.25, 0.5),
@@ -101,6 +225,7 @@ fn spawn_player(
                 Vec3::NEG_Y,
             )
             .with_max_time_of_impact(0.25),
+            DespawnOnRunEnd,
         ))
         .with_children(|commands| {
             commands.spawn((
@@ -112,10 +237,10 @@ fn spawn_player(
                     },
                     tonemapping: Tonemapping::SomewhatBoringDisplayTransform,
                     projection: Projection::Perspective(PerspectiveProjection {
-                        fov: 90.0f32.to_radians(),
+                        fov: settings.fov.to_radians(),
                         ..default()
                     }),
-                    transform: Transform::from_xyz(0.0, 0.75, 0.0),
+                    transform: Transform::from_xyz(0.0, CAMERA_HEIGHT, 0.0),
                     ..default()
                 },
                 ToonShaderMainCamera,
@@ -132,27 +257,22 @@ fn spawn_player(
         });
 }
 
+fn queue_actions(actions: Res<ActionState>, mut queued: ResMut<QueuedActions>) {
+    queued.jump |= actions.just_pressed(Action::Jump);
+    queued.dash |= actions.just_pressed(Action::Dash);
+}
 fn player_move(
-    time: Res<Time>,
-    key: Res<Input<KeyCode>>,
+    time: Res<Time<Physics>>,
+    actions: Res<ActionState>,
+    mut queued: ResMut<QueuedActions>,
     mut player_query: Query<(&Transform, &mut LinearVelocity, &ShapeHits), With<Player>>,
 ) {
+    let jump = std::mem::take(&mut queued.jump);
     for (transform, mut velocity, ground_caster_hits) in &mut player_query {
-        let mut direction = Vec3::ZERO;
-        if key.pressed(KeyCode::W) {
-            direction += transform.forward();
-        }
-        if key.pressed(KeyCode::A) {
-            direction += transform.left();
-        }
-        if key.pressed(KeyCode::S) {
-            direction += transform.back();
-        }
-        if key.pressed(KeyCode::D) {
-            direction += transform.right();
-        }
+        let direction =
+            transform.forward() * actions.movement.y + transform.right() * actions.movement.x;
         velocity.0 += direction * time.delta_seconds() * ACCELERATION;
-        if key.just_pressed(KeyCode::Space) && !ground_caster_hits.is_empty() {
+        if jump && !ground_caster_hits.is_empty() {
             velocity.0.y += JUMP_VELOCITY;
         }
     }
@@ -161,92 +281,183 @@ fn player_look(
     mut window: Query<&mut Window>,
     mut player: Query<(&mut Transform, &mut CameraLook), (With<Player>, Without<Camera3d>)>,
     mut camera: Query<&mut Transform, (Without<Player>, With<Camera3d>)>,
-    mouse: Res<Input<MouseButton>>,
-    key: Res<Input<KeyCode>>,
-    mut motion: EventReader<MouseMotion>,
+    actions: Res<ActionState>,
+    touch: Res<TouchControls>,
     mut physics: ResMut<Time<Physics>>,
+    settings: Res<Settings>,
 ) {
-    let mut window = window.single_mut();
-    if mouse.just_pressed(MouseButton::Left) {
-        window.cursor.grab_mode = CursorGrabMode::Locked;
-        window.cursor.visible = false;
+    // The run starts once you first click, which also grabs the cursor again if it was lost.
+    if actions.just_pressed(Action::Hook) {
+        grab_cursor(&mut window.single_mut(), &touch);
         physics.unpause();
     }
-    if key.just_pressed(KeyCode::Escape) {
-        window.cursor.grab_mode = CursorGrabMode::None;
-        window.cursor.visible = true;
-        physics.pause();
-    }
 
     let (mut player_transform, mut camera_look) = player.single_mut();
     let mut camera_transform = camera.single_mut();
 
-    if !window.cursor.visible {
-        let motion = motion
-            .read()
-            .fold(Vec2::ZERO, |vec2, event| vec2 + event.delta);
-
-        player_transform.rotate_y(motion.x * -0.001);
-        camera_transform.rotate_x(motion.y * -0.001);
-
-        camera_look.0.rotate_y(motion.x * -0.001);
-        camera_look.0.rotate_local_x(motion.y * -0.001);
+    if !physics.is_paused() {
+        let mut look = actions.look * settings.sensitivity;
+        if settings.invert_y {
+            look.y = -look.y;
+        }
+        camera_look.yaw -= look.x;
+        camera_look.pitch = (camera_look.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
     }
+    // The player only turns around, the camera looks up and down on top of that.
+    player_transform.rotation = Quat::from_rotation_y(camera_look.yaw);
+    camera_transform.rotation = Quat::from_rotation_x(camera_look.pitch);
 }
+#[allow(clippy::too_many_arguments)]
 fn player_create_hook(
+    mut commands: Commands,
     mut player: Query<
-        (&mut Player, &Transform, &CameraLook, Entity),
+        (
+            &mut Player,
+            &Transform,
+            &CameraLook,
+            Entity,
+            &mut LinearVelocity,
+        ),
         (With<Player>, Without<Camera3d>),
     >,
-    entities: Query<
-        &Handle<OutlineToonMaterial>,
+    mut entities: Query<
+        (
+            &Transform,
+            &mut Handle<OutlineToonMaterial>,
+            Option<&PaletteMaterial>,
+            Option<&GumballKind>,
+            Option<&Archetype>,
+            Option<&ElectricCooldown>,
+        ),
         (Without<Player>, Without<Camera3d>, With<Collider>),
     >,
+    mut gumball_velocities: Query<&mut LinearVelocity, (With<GumballKind>, Without<Player>)>,
     caster: SpatialQuery,
-    mouse: Res<Input<MouseButton>>,
+    actions: Res<ActionState>,
     mut toon_materials: ResMut<Assets<OutlineToonMaterial>>,
+    mut pool: ResMut<GumballPool>,
+    spatial_hash: Res<SpatialHash>,
     mut last_entity: Local<Option<Entity>>,
 ) {
-    let (mut player, player_transform, camera_look, player_entity) = player.single_mut();
+    let (mut player, player_transform, camera_look, player_entity, mut velocity) =
+        player.single_mut();
 
-    if *last_entity != player.hooked_onto {
-        if let Some(material_handle) = last_entity.map(|e| entities.get(e).ok()).flatten() {
-            if let Some(material) = toon_materials.get_mut(material_handle) {
-                material.outline_color = Color::NONE;
-            }
+    if *last_entity != player.hooked_onto.map(|anchor| anchor.entity) {
+        if let Some((_, mut material_handle, palette, _, _, _)) =
+            last_entity.and_then(|e| entities.get_mut(e).ok())
+        {
+            set_outline(&mut material_handle, palette, &mut toon_materials, false);
         }
     }
+    if player.stunned > 0.0 {
+        *last_entity = None;
+        return;
+    }
     if let Some(hit) = caster.cast_ray(
-        player_transform.translation,
-        camera_look.0.forward(),
+        CameraLook::eye(player_transform),
+        camera_look.forward(),
         40.0 + (player.upgrades.hook_range as f32 * 20.0),
         true,
         SpatialQueryFilter::new().without_entities([player_entity]),
     ) {
-        let Ok(material_handle) = entities.get(hit.entity) else {
+        let Ok((entity_transform, mut material_handle, palette, kind, archetype, cooldown)) =
+            entities.get_mut(hit.entity)
+        else {
             return;
         };
-        *last_entity = Some(hit.entity);
-        if let Some(material) = toon_materials.get_mut(material_handle) {
-            material.outline_color = Color::rgb_linear(100.0, 100.0, 100.0);
+        if cooldown.is_some() {
+            // It just zapped the hook and can't be hooked until it recharges.
+            *last_entity = None;
+            return;
         }
-        if mouse.just_pressed(MouseButton::Left) {
-            player.hooked_onto = Some(hit.entity);
+        *last_entity = Some(hit.entity);
+        set_outline(&mut material_handle, palette, &mut toon_materials, true);
+        if actions.just_pressed(Action::Hook) && kind == Some(&GumballKind::Explosive) {
+            // There's nothing left to hold onto once it goes off.
+            explode(
+                &mut commands,
+                &mut pool,
+                &spatial_hash,
+                &mut gumball_velocities,
+                hit.entity,
+                entity_transform.translation,
+                player_transform.translation,
+                &mut velocity,
+            );
+        } else if actions.just_pressed(Action::Hook)
+            && archetype == Some(&Archetype::ElectricSphere)
+        {
+            // The sphere zaps the hook away, only the sphere itself is left unhookable.
+            commands.entity(hit.entity).insert(ElectricCooldown::new());
+        } else if actions.just_pressed(Action::Hook) {
+            let hit_point =
+                CameraLook::eye(player_transform) + camera_look.forward() * hit.time_of_impact;
+            player.hooked_onto = Some(HookAnchor::from_world(
+                hit.entity,
+                entity_transform,
+                hit_point,
+                hit.normal,
+            ));
             player.dash = Some(());
         }
     }
 }
+/// Shows or hides the outline of a hookable object.
+///
+/// Gumballs share their materials, so they switch to the outlined one from their palette instead.
+fn set_outline(
+    material_handle: &mut Handle<OutlineToonMaterial>,
+    palette: Option<&PaletteMaterial>,
+    toon_materials: &mut Assets<OutlineToonMaterial>,
+    outlined: bool,
+) {
+    if let Some(palette) = palette {
+        let handle = if outlined {
+            &palette.outlined
+        } else {
+            &palette.plain
+        };
+        if material_handle != handle {
+            *material_handle = handle.clone();
+        }
+    } else if let Some(material) = toon_materials.get_mut(&*material_handle) {
+        material.outline_color = if outlined {
+            HOOK_OUTLINE_COLOR
+        } else {
+            Color::NONE
+        };
+    }
+}
+
+fn player_recover_from_stun(mut player: Query<&mut Player>, time: Res<Time>) {
+    if let Ok(mut player) = player.get_single_mut() {
+        player.stunned = (player.stunned - time.delta_seconds()).max(0.0);
+    }
+}
+
+fn player_switch_hook_mode(mut player: Query<&mut Player>, actions: Res<ActionState>) {
+    if actions.just_pressed(Action::SwitchHookMode) {
+        if let Ok(mut player) = player.get_single_mut() {
+            player.hook_mode = match player.hook_mode {
+                HookMode::Pull => HookMode::Rope,
+                HookMode::Rope => HookMode::Pull,
+            };
+        }
+    }
+}
 fn player_use_and_remove_hook(
-    mut player: Query<(&mut Player, &mut LinearVelocity, &Transform, &CameraLook), With<Player>>,
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Player, &Transform, &CameraLook), With<Player>>,
     mut entities: Query<
         (
             &Transform,
-            &Handle<OutlineToonMaterial>,
-            &mut LinearVelocity,
+            &mut Handle<OutlineToonMaterial>,
+            Option<&PaletteMaterial>,
         ),
         (Without<Player>, With<Collider>),
     >,
-    mouse: Res<Input<MouseButton>>,
+    mut joints: Query<&mut DistanceJoint>,
+    actions: Res<ActionState>,
     mut gizmos: Gizmos,
     mut toon_materials: ResMut<Assets<OutlineToonMaterial>>,
     time_physics: Res<Time<Physics>>,
@@ -254,58 +465,137 @@ fn player_use_and_remove_hook(
     if time_physics.is_paused() {
         return;
     }
-    if let Ok((mut player, mut velocity, transform, look)) = player.get_single_mut() {
-        if let Some(hooked_onto) = player.hooked_onto {
-            if let Ok((entity_transform, material_handle, mut other_velocity)) =
-                entities.get_mut(hooked_onto)
+    let hook_held = actions.pressed(Action::Hook);
+    if let Ok((player_entity, mut player, transform, look)) = player.get_single_mut() {
+        if player.hook_mode != HookMode::Rope || player.hooked_onto.is_none() {
+            remove_rope(&mut commands, &mut player);
+        }
+        if let Some(anchor) = player.hooked_onto {
+            if let Ok((entity_transform, mut material_handle, palette)) =
+                entities.get_mut(anchor.entity)
             {
+                let anchor_point = anchor.world_point(entity_transform);
                 gizmos.line(
-                    transform.translation + look.0.forward() + Vec3::new(0.0, 0.75, 0.0),
-                    entity_transform.translation,
+                    CameraLook::eye(transform) + look.forward(),
+                    anchor_point,
                     Color::WHITE,
                 );
-                let direction = (entity_transform.translation - transform.translation).normalize();
+                gizmos.circle(
+                    anchor_point,
+                    anchor.world_normal(entity_transform),
+                    0.3,
+                    Color::WHITE,
+                );
+                let distance = anchor_point.distance(transform.translation);
 
-                if entity_transform
-                    .translation
-                    .distance_squared(transform.translation)
-                    > 5.0
-                {
-                    velocity.0 += direction
-                        * HOOK_SPEED
-                        * ((player.upgrades.hook_strength as f32 + 4.0) * 0.25);
-                    other_velocity.0 -= direction
-                        * HOOK_SPEED
-                        * 0.2
-                        * ((player.upgrades.hook_strength as f32 + 4.0) * 0.25);
-                }
+                match player.hook_mode {
+                    // The pull is applied on the physics timestep by `player_pull_hook`.
+                    HookMode::Pull => {}
+                    HookMode::Rope => {
+                        let max_length = 40.0 + (player.upgrades.hook_range as f32 * 20.0);
+                        let reel_speed =
+                            ROPE_REEL_SPEED * ((player.upgrades.hook_strength as f32 + 4.0) * 0.25);
+                        let rope = player.rope.get_or_insert_with(|| Rope {
+                            joint: commands
+                                .spawn((
+                                    DistanceJoint::new(player_entity, anchor.entity)
+                                        .with_local_anchor_2(anchor.point)
+                                        .with_limits(0.0, distance)
+                                        // xpbd always solves towards the rest length, so it
+                                        // has to match the limit or the rope collapses.
+                                        .with_rest_length(distance),
+                                    DespawnOnRunEnd,
+                                ))
+                                .id(),
+                            length: distance,
+                        });
+                        let length = (rope.length - actions.reel * reel_speed)
+                            .clamp(MIN_ROPE_LENGTH, max_length.max(MIN_ROPE_LENGTH));
 
-                if !mouse.pressed(MouseButton::Left) {
-                    if let Some(material) = toon_materials.get_mut(material_handle) {
-                        material.outline_color = Color::NONE;
+                        if length != rope.length {
+                            rope.length = length;
+                            if let Ok(mut joint) = joints.get_mut(rope.joint) {
+                                if let Some(limits) = &mut joint.length_limits {
+                                    limits.max = length;
+                                }
+                                joint.rest_length = length;
+                            }
+                        }
                     }
                 }
+
+                if !hook_held {
+                    set_outline(&mut material_handle, palette, &mut toon_materials, false);
+                }
+            } else {
+                // The hooked object is gone, so there is nothing left to swing on.
+                remove_rope(&mut commands, &mut player);
             }
-            if !mouse.pressed(MouseButton::Left) {
+            if !hook_held {
                 player.hooked_onto = None;
+                remove_rope(&mut commands, &mut player);
             }
         }
     } else {
         error!("There is no player... wtf");
     }
 }
+/// Despawns the player's rope joint, if they have one.
+fn remove_rope(commands: &mut Commands, player: &mut Player) {
+    if let Some(rope) = player.rope.take() {
+        commands.entity(rope.joint).despawn();
+    }
+}
+/// Pulls the player towards the hooked object while in [`HookMode::Pull`].
+fn player_pull_hook(
+    mut player: Query<(&Player, &mut LinearVelocity, &Transform)>,
+    mut entities: Query<
+        (&Transform, &RigidBody, &mut LinearVelocity),
+        (Without<Player>, With<Collider>),
+    >,
+    time: Res<Time<Physics>>,
+) {
+    let Ok((player, mut velocity, transform)) = player.get_single_mut() else {
+        return;
+    };
+    let Some(anchor) = player
+        .hooked_onto
+        .filter(|_| player.hook_mode == HookMode::Pull)
+    else {
+        return;
+    };
+    let Ok((entity_transform, rigid_body, mut other_velocity)) = entities.get_mut(anchor.entity)
+    else {
+        return;
+    };
+    let offset = anchor.world_point(entity_transform) - transform.translation;
+    if offset.length_squared() <= 5.0 {
+        return;
+    }
+    let direction = offset.normalize();
+    let pull = hook_pull(player.upgrades.hook_strength, time.delta_seconds());
+    velocity.0 += direction * pull;
+    // Platforms keep to their motion, only loose objects get pulled back.
+    if rigid_body.is_dynamic() {
+        other_velocity.0 -= direction * pull * 0.2;
+    }
+}
+/// How much the hook speeds the player up over `delta` seconds of pulling, with `hook_strength` upgrades.
+fn hook_pull(hook_strength: u64, delta: f32) -> f32 {
+    HOOK_ACCELERATION * ((hook_strength as f32 + 4.0) * 0.25) * delta
+}
 fn player_dash(
     mut player: Query<(&mut LinearVelocity, &mut Player, &CameraLook), With<Player>>,
-    mouse: Res<Input<MouseButton>>,
+    mut queued: ResMut<QueuedActions>,
     mut last_dash_time: Local<f64>,
-    time: Res<Time>,
+    time: Res<Time<Physics>>,
 ) {
+    let dash = std::mem::take(&mut queued.dash);
     if let Ok((mut velocity, mut player, look)) = player.get_single_mut() {
         let is_on_cooldown = (time.elapsed_seconds_f64() - *last_dash_time) < DASH_COOLDOWN;
-        if mouse.just_pressed(MouseButton::Right) && player.dash.take().is_some() && !is_on_cooldown
-        {
+        if dash && player.dash.take().is_some() && !is_on_cooldown {
             velocity.0 +=
-                look.0.forward() * (DASH_POWER + (player.upgrades.dash_strength as f32 * 30.0));
+                look.forward() * (DASH_POWER + (player.upgrades.dash_strength as f32 * 30.0));
             *last_dash_time = time.elapsed_seconds_f64();
         }
     }
@@ -318,28 +608,242 @@ fn player_update_score(player: Query<&Transform, With<Player>>, mut score: ResMu
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn player_colliding_entities(
     mut commands: Commands,
-    player: Query<&CollidingEntities, With<Player>>,
-    thingamajigs: Query<&Thingajamig>,
+    mut player: Query<
+        (
+            &CollidingEntities,
+            &Transform,
+            &mut LinearVelocity,
+            &mut Player,
+        ),
+        With<Player>,
+    >,
+    mut thingamajigs: Query<&mut Thingajamig>,
+    voxels: Query<&VoxelPoints>,
+    // Gumballs lose their collider once they're back in the pool.
+    gumballs: Query<(&GumballKind, &Transform), (With<Collider>, Without<Player>)>,
+    mut gumball_velocities: Query<&mut LinearVelocity, (With<GumballKind>, Without<Player>)>,
+    hazards: Query<&Archetype, Without<GumballKind>>,
+    mut pool: ResMut<GumballPool>,
+    spatial_hash: Res<SpatialHash>,
+    actions: Res<ActionState>,
     mut score: ResMut<Score>,
+    mut last_velocity: Local<Vec3>,
 ) {
-    if let Ok(CollidingEntities(entities)) = player.get_single() {
-        for entity in entities {
-            if let Ok(Thingajamig(thingamajig_entities)) = thingamajigs.get(*entity) {
-                commands.entity(*entity).despawn();
-                score.to_be_added.destruction += 10;
-                for thingajamig_entity in thingamajig_entities {
-                    commands
-                        .entity(*thingajamig_entity)
-                        .insert(RigidBody::Dynamic)
-                        .insert(SleepingDisabled)
-                        .insert(OutlineToonFadeOut::new(Duration::from_secs(5)))
-                        .remove::<Sleeping>();
+    let Ok((CollidingEntities(entities), transform, mut velocity, mut player)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    for (entity, (kind, gumball_transform)) in entities
+        .iter()
+        .filter_map(|entity| Some((*entity, gumballs.get(*entity).ok()?)))
+    {
+        match kind {
+            // Bouncing is taken care of by the restitution of the gumball.
+            GumballKind::Plain | GumballKind::Bouncy => {}
+            GumballKind::Sticky => {
+                // Jumping or hooking something else lets go of it.
+                if player.hooked_onto.is_none() && !actions.just_pressed(Action::Jump) {
+                    if let Ok(gumball_velocity) = gumball_velocities.get(entity) {
+                        velocity.0 = gumball_velocity.0;
+                    }
                 }
-                // for entity in thingamajig_entities {
-                //     // commands.insert
-                // }
+            }
+            GumballKind::Explosive => explode(
+                &mut commands,
+                &mut pool,
+                &spatial_hash,
+                &mut gumball_velocities,
+                entity,
+                gumball_transform.translation,
+                transform.translation,
+                &mut velocity,
+            ),
+            GumballKind::Boost => {
+                player.dash = Some(());
+                pool.release(&mut commands, entity);
+            }
+            GumballKind::Spiked => {
+                let away =
+                    (transform.translation - gumball_transform.translation).normalize_or_zero();
+                velocity.0 = away * SPIKE_KNOCKBACK;
+                player.stun(SPIKE_STUN);
+                pool.release(&mut commands, entity);
+            }
+        }
+    }
+    for archetype in hazards.iter_many(entities) {
+        match archetype {
+            Archetype::ElectricSphere => player.stun(ELECTRIC_STUN),
+            Archetype::Anvil => velocity.y = velocity.y.min(-ANVIL_PUSH),
+            _ => {}
+        }
+    }
+    // The collision already slowed the player down, so the speed before it is used too.
+    let speed = velocity.length().max(last_velocity.length());
+    *last_velocity = velocity.0;
+    if speed < MIN_BREAK_SPEED {
+        return;
+    }
+    let radius = (BREAK_REACH + speed * BREAK_RADIUS_PER_SPEED).min(MAX_BREAK_RADIUS);
+    for mut thingamajig in thingamajigs.iter_mut() {
+        if !entities.contains(&thingamajig.body) {
+            continue;
+        }
+        let (broken, falling) = thingamajig.break_at(transform.translation, radius);
+        if broken.is_empty() {
+            continue;
+        }
+        match thingamajig.collider() {
+            Some(collider) => {
+                commands.entity(thingamajig.body).insert(collider);
+            }
+            None => commands.entity(thingamajig.body).despawn(),
+        }
+        for voxel in broken.iter().chain(&falling) {
+            if let Ok(VoxelPoints(points)) = voxels.get(voxel.entity) {
+                score.to_be_added.destruction += points;
+            }
+        }
+        // Voxels hit by the player fly away from it, the ones left hanging fall.
+        for voxel in broken {
+            let direction = (voxel.position - transform.translation).normalize_or_zero();
+            commands.entity(voxel.entity).insert((
+                RigidBody::Dynamic,
+                Collider::cuboid(1.5, 1.5, 1.5),
+                ColliderDensity(0.25),
+                GravityScale(0.0),
+                LinearVelocity(direction * speed * KNOCK_STRENGTH),
+                SleepingDisabled,
+                OutlineToonFadeOut::new(Duration::from_secs(5)),
+            ));
+        }
+        for voxel in falling {
+            commands.entity(voxel.entity).insert((
+                RigidBody::Dynamic,
+                Collider::cuboid(1.5, 1.5, 1.5),
+                ColliderDensity(0.25),
+                SleepingDisabled,
+                OutlineToonFadeOut::new(Duration::from_secs(5)),
+            ));
+        }
+    }
+}
+
+/// Blows up an explosive gumball, pushing the player and the gumballs around it away.
+#[allow(clippy::too_many_arguments)]
+fn explode(
+    commands: &mut Commands,
+    pool: &mut GumballPool,
+    spatial_hash: &SpatialHash,
+    gumball_velocities: &mut Query<&mut LinearVelocity, (With<GumballKind>, Without<Player>)>,
+    gumball: Entity,
+    center: Vec3,
+    player_position: Vec3,
+    player_velocity: &mut LinearVelocity,
+) {
+    let push = |position: Vec3| {
+        let offset = position - center;
+        let falloff = (1.0 - offset.length() / EXPLOSION_RADIUS).max(0.0);
+        offset.normalize_or_zero() * EXPLOSION_STRENGTH * falloff
+    };
+    player_velocity.0 += push(player_position);
+    for (entity, position) in spatial_hash.within(Archetype::Gumball, center, EXPLOSION_RADIUS) {
+        if let Ok(mut velocity) = gumball_velocities.get_mut(entity) {
+            velocity.0 += push(position);
+        }
+    }
+    pool.release(commands, gumball);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{catch_up_physics, GameState, PauseState};
+    use bevy::time::TimeUpdateStrategy;
+
+    /// Runs a second of frames at `hz` frames per second with the player hooked onto
+    /// a far away wall, returning the player's velocity.
+    fn pull_for_a_second(hook_strength: u64, hz: u32) -> Vec3 {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            bevy::scene::ScenePlugin,
+            PhysicsPlugins::default(),
+        ))
+        .init_asset::<Mesh>()
+        .add_state::<GameState>()
+        .add_state::<PauseState>()
+        .insert_resource(NextState(Some(GameState::Playing)))
+        .insert_resource(Gravity(Vec3::ZERO))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / hz as f64,
+        )))
+        .add_systems(Startup, catch_up_physics)
+        .add_systems(
+            PhysicsSchedule,
+            player_pull_hook
+                .before(PhysicsStepSet::BroadPhase)
+                .run_if(gameplay_running),
+        );
+
+        let wall = app
+            .world
+            .spawn((
+                TransformBundle::from_transform(Transform::from_xyz(1000.0, 0.0, 0.0)),
+                RigidBody::Static,
+                Collider::cuboid(1.0, 1.0, 1.0),
+            ))
+            .id();
+        let player = app
+            .world
+            .spawn((
+                TransformBundle::default(),
+                RigidBody::Dynamic,
+                Collider::ball(0.5),
+                Player {
+                    hooked_onto: Some(HookAnchor {
+                        entity: wall,
+                        point: Vec3::ZERO,
+                        normal: Vec3::NEG_X,
+                    }),
+                    hook_mode: HookMode::Pull,
+                    rope: None,
+                    dash: None,
+                    upgrades: PlayerUpgrades {
+                        hook_strength,
+                        ..default()
+                    },
+                    stunned: 0.0,
+                },
+            ))
+            .id();
+
+        // The first update only starts the clock.
+        app.update();
+        for _ in 0..hz {
+            app.update();
+        }
+        app.world.get::<LinearVelocity>(player).unwrap().0
+    }
+
+    #[test]
+    fn hook_pull_is_frame_rate_independent() {
+        for hook_strength in [0, 3] {
+            let speed = HOOK_ACCELERATION * (hook_strength as f32 + 4.0) * 0.25;
+            for hz in [30, 60, 240] {
+                let velocity = pull_for_a_second(hook_strength, hz);
+                // A physics step more or less is fine, the frame rate just mustn't scale the pull.
+                assert!(
+                    velocity.distance(Vec3::X * speed) < speed * 0.05,
+                    "{velocity} at {hz} Hz, expected {speed} along x"
+                );
             }
         }
     }
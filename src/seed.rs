@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{high_scores::now, GameState};
+
+/// Where the seed of the next run comes from.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SeedSource {
+    /// A new random seed for every run.
+    Random,
+    /// The same seed every run, given with `--seed <seed>` on the command line or typed into the menu.
+    Fixed(u64),
+    /// A seed that changes once a day, so everyone plays the same world that day.
+    Daily,
+}
+impl SeedSource {
+    /// Reads the `--seed <seed>` command line argument, or picks a random seed without it.
+    fn from_args() -> Self {
+        let mut args = std::env::args().skip_while(|arg| arg != "--seed").skip(1);
+        match args.next().map(|seed| seed.parse()) {
+            Some(Ok(seed)) => SeedSource::Fixed(seed),
+            Some(Err(error)) => {
+                warn!("Invalid --seed argument: {error}");
+                SeedSource::Random
+            }
+            None => SeedSource::Random,
+        }
+    }
+}
+
+/// The random number generators used to generate the world of the current run.
+///
+/// Every spawn group of the spawn table gets its own stream,
+/// so how often one group spawns doesn't change what the others spawn.
+/// Every chunk of the world gets its own stream too, so it's the same no matter when it's loaded.
+#[derive(Resource)]
+pub struct WorldRng {
+    /// The seed of the current run.
+    pub seed: u64,
+    streams: Vec<ChaCha8Rng>,
+}
+impl WorldRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: Vec::new(),
+        }
+    }
+    /// The generator of stream `index`, created the first time it's used.
+    pub fn stream(&mut self, index: usize) -> &mut ChaCha8Rng {
+        while self.streams.len() <= index {
+            let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+            rng.set_stream(self.streams.len() as u64);
+            self.streams.push(rng);
+        }
+        &mut self.streams[index]
+    }
+    /// A new generator for the chunk at `chunk`.
+    pub fn chunk(&self, chunk: IVec3) -> ChaCha8Rng {
+        // Each coordinate gets 21 bits, and the highest bit keeps them apart from the spawn group streams.
+        let bits = |coordinate: i32| coordinate as u64 & 0x1F_FFFF;
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(1 << 63 | bits(chunk.x) << 42 | bits(chunk.y) << 21 | bits(chunk.z));
+        rng
+    }
+}
+
+pub struct SeedPlugin;
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SeedSource::from_args())
+            .insert_resource(WorldRng::new(0))
+            .add_systems(OnEnter(GameState::Playing), seed_world);
+    }
+}
+
+/// The seed of today's daily run, the number of days since the unix epoch.
+pub fn daily_seed() -> u64 {
+    now() / 86400
+}
+
+fn seed_world(source: Res<SeedSource>, mut rng: ResMut<WorldRng>) {
+    let seed = match *source {
+        SeedSource::Random => rand::thread_rng().gen(),
+        SeedSource::Fixed(seed) => seed,
+        SeedSource::Daily => daily_seed(),
+    };
+    info!("Starting a run with seed {seed}");
+    *rng = WorldRng::new(seed);
+}
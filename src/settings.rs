@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_toon_shader::ToonShaderMainCamera;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// The name of the file the settings are saved to.
+const SETTINGS_FILE: &str = "settings";
+
+/// Player options, changed from the settings panel in the pause menu and saved to the settings file.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The vertical field of view of the camera, in degrees.
+    pub fov: f32,
+    /// Whether to show hints about the controls during a run.
+    pub hints: bool,
+    /// How fast the camera turns, relative to the default speed.
+    pub sensitivity: f32,
+    /// Whether looking up and down is swapped.
+    pub invert_y: bool,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fov: 90.0,
+            hints: true,
+            sensitivity: 1.0,
+            invert_y: false,
+        }
+    }
+}
+impl Settings {
+    pub const MIN_FOV: f32 = 60.0;
+    pub const MAX_FOV: f32 = 120.0;
+    pub const MIN_SENSITIVITY: f32 = 0.25;
+    pub const MAX_SENSITIVITY: f32 = 4.0;
+
+    /// Brings the values back into their ranges, in case the settings file was edited by hand.
+    fn clamp(mut self) -> Self {
+        let default = Self::default();
+        if self.fov.is_nan() {
+            self.fov = default.fov;
+        }
+        if self.sensitivity.is_nan() {
+            self.sensitivity = default.sensitivity;
+        }
+        self.fov = self.fov.clamp(Self::MIN_FOV, Self::MAX_FOV);
+        self.sensitivity = self
+            .sensitivity
+            .clamp(Self::MIN_SENSITIVITY, Self::MAX_SENSITIVITY);
+        self
+    }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = storage::load::<Settings>(SETTINGS_FILE).unwrap_or_default();
+        app.insert_resource(settings.clamp())
+            .add_systems(Update, (apply_settings, save_settings));
+    }
+}
+
+fn apply_settings(
+    settings: Res<Settings>,
+    mut cameras: Query<&mut Projection, With<ToonShaderMainCamera>>,
+    added_cameras: Query<(), Added<ToonShaderMainCamera>>,
+) {
+    if !settings.is_changed() && added_cameras.is_empty() {
+        return;
+    }
+    for mut projection in cameras.iter_mut() {
+        if let Projection::Perspective(perspective) = &mut *projection {
+            perspective.fov = settings.fov.to_radians();
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        storage::save(SETTINGS_FILE, &*settings);
+    }
+}
@@ -5,13 +5,17 @@ use bevy::{
 };
 use bevy_xpbd_3d::plugins::setup::{Physics, PhysicsTime};
 
-use crate::{hud::Score, materials::RoundedRectangleMaterial, player::Player, GameState};
-
-#[derive(Resource, Default)]
-pub struct PointsSpent(pub u64);
+use crate::{
+    actions::{Action, ActionState, InputBindings},
+    gameplay_running,
+    materials::RoundedRectangleMaterial,
+    pickups::Coins,
+    player::Player,
+    DespawnOnRunEnd,
+};
 
 #[derive(Resource, Default)]
-pub struct IsShopping(bool);
+pub struct IsShopping(pub bool);
 
 #[derive(Component)]
 pub struct Shop;
@@ -28,20 +32,18 @@ pub struct DashStrengthText;
 pub struct ShopPlugin;
 impl Plugin for ShopPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<IsShopping>()
-            .init_resource::<PointsSpent>()
-            .add_systems(
-                Update,
-                ((enter_exit_shop, do_upgrades).run_if(in_state(GameState::Playing)),),
-            );
+        app.init_resource::<IsShopping>().add_systems(
+            Update,
+            ((enter_exit_shop, do_upgrades).run_if(gameplay_running),),
+        );
     }
 }
 fn enter_exit_shop(
     mut shopping: ResMut<IsShopping>,
-    key: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
     mut commands: Commands,
 ) {
-    if key.just_pressed(KeyCode::Tab) {
+    if actions.just_pressed(Action::Shop) {
         if shopping.0 {
             shopping.0 = false;
             commands.add(DespawnShop);
@@ -58,6 +60,7 @@ impl Command for SetupShop {
         fn setup_shop(
             mut commands: Commands,
             asset_server: Res<AssetServer>,
+            bindings: Res<InputBindings>,
             mut rectangles: ResMut<Assets<RoundedRectangleMaterial>>,
         ) {
             commands
@@ -86,17 +89,24 @@ impl Command for SetupShop {
                         ..default()
                     },
                     Shop,
+                    DespawnOnRunEnd,
                 ))
                 .with_children(|commands| {
+                    let key_image = |action| {
+                        UiImage::new(
+                            bindings
+                                .icon(action)
+                                .map(|icon| asset_server.load(icon))
+                                .unwrap_or_default(),
+                        )
+                    };
                     let style = TextStyle {
                         font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
                         font_size: 30.0,
                         color: Color::WHITE,
                     };
                     commands.spawn(ImageBundle {
-                        image: UiImage::new(
-                            asset_server.load("textures/keyboardmouse/1_Key_Dark.png"),
-                        ),
+                        image: key_image(Action::Buy1),
                         ..default()
                     });
                     commands.spawn((
@@ -105,14 +115,12 @@ impl Command for SetupShop {
                             TextSection::new("?", style.clone()),
                             TextSection::new(" - ", style.clone()),
                             TextSection::new("?", style.clone()),
-                            TextSection::new(" points", style.clone()),
+                            TextSection::new(" coins", style.clone()),
                         ]),
                         HookRangeText,
                     ));
                     commands.spawn(ImageBundle {
-                        image: UiImage::new(
-                            asset_server.load("textures/keyboardmouse/2_Key_Dark.png"),
-                        ),
+                        image: key_image(Action::Buy2),
                         style: Style {
                             margin: UiRect::left(Val::Px(20.0)),
                             ..default()
@@ -125,14 +133,12 @@ impl Command for SetupShop {
                             TextSection::new("?", style.clone()),
                             TextSection::new(" - ", style.clone()),
                             TextSection::new("?", style.clone()),
-                            TextSection::new(" points", style.clone()),
+                            TextSection::new(" coins", style.clone()),
                         ]),
                         HookStrengthText,
                     ));
                     commands.spawn(ImageBundle {
-                        image: UiImage::new(
-                            asset_server.load("textures/keyboardmouse/3_Key_Dark.png"),
-                        ),
+                        image: key_image(Action::Buy3),
                         style: Style {
                             margin: UiRect::left(Val::Px(20.0)),
                             ..default()
@@ -145,7 +151,7 @@ impl Command for SetupShop {
                             TextSection::new("?", style.clone()),
                             TextSection::new(" - ", style.clone()),
                             TextSection::new("?", style.clone()),
-                            TextSection::new(" points", style.clone()),
+                            TextSection::new(" coins", style.clone()),
                         ]),
                         DashStrengthText,
                     ));
@@ -168,10 +174,9 @@ impl Command for DespawnShop {
 }
 
 fn do_upgrades(
-    key: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
     is_shopping: Res<IsShopping>,
-    points: Res<Score>,
-    mut points_spent: ResMut<PointsSpent>,
+    mut coins: ResMut<Coins>,
     mut player: Query<&mut Player>,
     mut hook_range_text: Query<
         &mut Text,
@@ -202,7 +207,7 @@ fn do_upgrades(
         return;
     }
     let player_upgrades = &mut player.single_mut().upgrades;
-    let points_to_be_spent = points.current.total() - points_spent.0;
+    let balance = coins.balance();
     let get_price = |x| (x * x * 3) + 10;
 
     if let Ok(mut hook_range_text) = hook_range_text.get_single_mut() {
@@ -218,19 +223,19 @@ fn do_upgrades(
         dash_strength_text.sections[3].value = get_price(player_upgrades.dash_strength).to_string();
     }
 
-    if key.just_pressed(KeyCode::Key1) {
-        if points_to_be_spent > get_price(player_upgrades.hook_range) {
-            points_spent.0 += get_price(player_upgrades.hook_range);
+    if actions.just_pressed(Action::Buy1) {
+        if balance > get_price(player_upgrades.hook_range) {
+            coins.spent += get_price(player_upgrades.hook_range);
             player_upgrades.hook_range += 1;
         }
-    } else if key.just_pressed(KeyCode::Key2) {
-        if points_to_be_spent > get_price(player_upgrades.hook_strength) {
-            points_spent.0 += get_price(player_upgrades.hook_strength);
+    } else if actions.just_pressed(Action::Buy2) {
+        if balance > get_price(player_upgrades.hook_strength) {
+            coins.spent += get_price(player_upgrades.hook_strength);
             player_upgrades.hook_strength += 1;
         }
-    } else if key.just_pressed(KeyCode::Key3) {
-        if points_to_be_spent > get_price(player_upgrades.dash_strength) {
-            points_spent.0 += get_price(player_upgrades.dash_strength);
+    } else if actions.just_pressed(Action::Buy3) {
+        if balance > get_price(player_upgrades.dash_strength) {
+            coins.spent += get_price(player_upgrades.dash_strength);
             player_upgrades.dash_strength += 1;
         }
     }
@@ -0,0 +1,83 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_3d::PhysicsSet;
+
+use crate::spawn_table::Archetype;
+
+/// The size of the cells objects are sorted into.
+pub const CELL_SIZE: f32 = 50.0;
+
+/// Finds spawned objects by their archetype and position.
+///
+/// Objects are added when they're spawned, and it keeps track of them as they move and despawn.
+#[derive(Resource, Default)]
+pub struct SpatialHash {
+    cells: HashMap<(Archetype, IVec3), Vec<(Entity, Vec3)>>,
+    /// The archetype and cell of every object in the hash.
+    entries: HashMap<Entity, (Archetype, IVec3)>,
+}
+impl SpatialHash {
+    fn cell_at(position: Vec3) -> IVec3 {
+        (position / CELL_SIZE).floor().as_ivec3()
+    }
+    /// Adds an object, or moves it if it's already in the hash.
+    pub fn insert(&mut self, entity: Entity, archetype: Archetype, position: Vec3) {
+        self.remove(entity);
+        let cell = Self::cell_at(position);
+        self.cells
+            .entry((archetype, cell))
+            .or_default()
+            .push((entity, position));
+        self.entries.insert(entity, (archetype, cell));
+    }
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(key) = self.entries.remove(&entity) else {
+            return;
+        };
+        if let Some(objects) = self.cells.get_mut(&key) {
+            objects.retain(|(other, _)| *other != entity);
+            if objects.is_empty() {
+                self.cells.remove(&key);
+            }
+        }
+    }
+    /// The objects of `archetype` within `radius` of `position`, along with their positions.
+    pub fn within(
+        &self,
+        archetype: Archetype,
+        position: Vec3,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec3)> + '_ {
+        let min = Self::cell_at(position - radius);
+        let max = Self::cell_at(position + radius);
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| (x, y)))
+            .flat_map(move |(x, y)| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+            .filter_map(move |cell| self.cells.get(&(archetype, cell)))
+            .flatten()
+            .copied()
+            .filter(move |(_, other)| other.distance_squared(position) < radius * radius)
+    }
+}
+
+pub struct SpatialHashPlugin;
+
+/// This plugin keeps the [`SpatialHash`] up to date.
+impl Plugin for SpatialHashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialHash>()
+            .add_systems(PostUpdate, update_spatial_hash.after(PhysicsSet::Sync));
+    }
+}
+
+fn update_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    moved: Query<(Entity, &Archetype, &Transform), Changed<Transform>>,
+    mut despawned: RemovedComponents<Archetype>,
+) {
+    for entity in despawned.read() {
+        spatial_hash.remove(entity);
+    }
+    for (entity, archetype, transform) in moved.iter() {
+        spatial_hash.insert(entity, *archetype, transform.translation);
+    }
+}
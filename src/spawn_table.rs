@@ -0,0 +1,250 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{gumball_kinds::GumballKind, platforms::PlatformMotion, structure::Structure};
+
+/// Describes what spawns around the player and how often, every biome has its own.
+///
+/// Loaded from `.spawn_table.ron` files, which get hot reloaded when the `dev` feature is enabled.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SpawnTable {
+    /// Objects placed in every chunk of the biome when it's loaded.
+    #[serde(default)]
+    pub chunk_objects: Vec<ChunkObject>,
+    /// Objects spawning around the player on a timer.
+    #[serde(default)]
+    pub groups: Vec<SpawnGroup>,
+}
+
+/// An object placed in the chunks of the world, the same way every time for the same seed.
+#[derive(Deserialize)]
+pub struct ChunkObject {
+    pub archetype: Archetype,
+    /// How many random spots in every chunk are tried for the object.
+    pub attempts: u32,
+    /// How likely the object is placed at each spot.
+    #[serde(default = "always")]
+    pub chance: f32,
+    /// The object is only placed above this height.
+    #[serde(default)]
+    pub from_height: Option<f32>,
+    /// If set, the chance gets divided by the chunk's height divided by this.
+    #[serde(default)]
+    pub height_scale: Option<f32>,
+    /// The minimum distance to other objects of the same archetype. It isn't placed if one is closer.
+    #[serde(default)]
+    pub min_spacing: f32,
+    /// How a [`Archetype::Platform`] moves.
+    #[serde(default)]
+    pub motion: PlatformMotion,
+    /// The kinds a [`Archetype::Gumball`] is picked from and their weights, it's plain if there are none.
+    #[serde(default)]
+    pub gumball_kinds: Vec<(GumballKind, f32)>,
+    /// The paths of the structures a [`Archetype::Thingamajig`] is picked from.
+    #[serde(default)]
+    pub structures: Vec<String>,
+    /// The handles of the `structures`, loaded along with the spawn table.
+    #[serde(skip)]
+    pub structure_handles: Vec<Handle<Structure>>,
+}
+impl ChunkObject {
+    /// What gets spawned for this object.
+    pub fn settings(&self) -> ObjectSettings<'_> {
+        ObjectSettings {
+            archetype: self.archetype,
+            structure_handles: &self.structure_handles,
+            motion: &self.motion,
+            gumball_kinds: &self.gumball_kinds,
+        }
+    }
+    /// The chance of the object being placed at each spot of a chunk at `height`.
+    pub fn chance(&self, height: f32) -> f32 {
+        match self.height_scale {
+            Some(scale) if height > 0.0 => self.chance * scale / height,
+            _ => self.chance,
+        }
+    }
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+/// A set of objects which spawn on their own timer.
+#[derive(Deserialize)]
+pub struct SpawnGroup {
+    /// How often the group spawns depending on the player's height.
+    ///
+    /// The band with the highest `from_height` below the player is used,
+    /// or the first band if the player is below all of them.
+    pub bands: Vec<AltitudeBand>,
+    /// Every time the group spawns, one of these is picked based on its weight.
+    pub objects: Vec<SpawnEntry>,
+}
+impl SpawnGroup {
+    /// The time between spawns at `height`, or `None` if nothing spawns there.
+    pub fn interval(&self, height: f32) -> Option<f64> {
+        let band = self
+            .bands
+            .iter()
+            .filter(|band| band.from_height <= height)
+            .max_by(|a, b| a.from_height.total_cmp(&b.from_height))
+            .or(self.bands.first())?;
+        let interval = band.interval?;
+        let interval = match band.height_scale {
+            Some(scale) => interval * (height as f64 / scale),
+            None => interval,
+        };
+        // A zero interval would spawn forever.
+        (interval > 0.0).then_some(interval)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AltitudeBand {
+    /// The height this band starts at.
+    pub from_height: f32,
+    /// Seconds between spawns, `None` disables the group in this band.
+    pub interval: Option<f64>,
+    /// If set, the interval gets multiplied by the player's height divided by this.
+    #[serde(default)]
+    pub height_scale: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct SpawnEntry {
+    pub archetype: Archetype,
+    /// How likely this entry is picked relative to the other entries in the group.
+    pub weight: f32,
+    /// Where the object spawns, relative to the player.
+    #[serde(default)]
+    pub volume: SpawnVolume,
+    /// The minimum distance to other objects of the same archetype. It isn't spawned if one is closer.
+    #[serde(default)]
+    pub min_spacing: f32,
+    /// How a [`Archetype::Platform`] moves.
+    #[serde(default)]
+    pub motion: PlatformMotion,
+    /// The kinds a [`Archetype::Gumball`] is picked from and their weights, it's plain if there are none.
+    #[serde(default)]
+    pub gumball_kinds: Vec<(GumballKind, f32)>,
+    /// The paths of the structures a [`Archetype::Thingamajig`] is picked from.
+    #[serde(default)]
+    pub structures: Vec<String>,
+    /// The handles of the `structures`, loaded along with the spawn table.
+    #[serde(skip)]
+    pub structure_handles: Vec<Handle<Structure>>,
+}
+
+impl SpawnEntry {
+    /// What gets spawned for this entry.
+    pub fn settings(&self) -> ObjectSettings<'_> {
+        ObjectSettings {
+            archetype: self.archetype,
+            structure_handles: &self.structure_handles,
+            motion: &self.motion,
+            gumball_kinds: &self.gumball_kinds,
+        }
+    }
+}
+
+/// Everything about an entry of a spawn table needed to spawn its object.
+#[derive(Clone, Copy)]
+pub struct ObjectSettings<'a> {
+    pub archetype: Archetype,
+    pub structure_handles: &'a [Handle<Structure>],
+    pub motion: &'a PlatformMotion,
+    pub gumball_kinds: &'a [(GumballKind, f32)],
+}
+
+/// A box relative to the player which objects spawn in.
+#[derive(Deserialize, Default)]
+pub struct SpawnVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+impl SpawnVolume {
+    /// Picks a random point in the volume, relative to the player.
+    pub fn sample(&self, rng: &mut impl Rng) -> Vec3 {
+        let mut axis = |min: f32, max: f32| {
+            if min < max {
+                rng.gen_range(min..max)
+            } else {
+                min
+            }
+        };
+        Vec3::new(
+            axis(self.min.x, self.max.x),
+            axis(self.min.y, self.max.y),
+            axis(self.min.z, self.max.z),
+        )
+    }
+}
+
+/// The kinds of objects a spawn table can spawn.
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Archetype {
+    /// Spawns nothing, used to make a group spawn less often.
+    Nothing,
+    /// A large static gray sphere.
+    Sphere,
+    /// A small colorful ball falling down.
+    Gumball,
+    /// A big destructible structure made of voxels, picked from the entry's `structures`.
+    Thingamajig,
+    /// A flat kinematic slab moving along the entry's `motion`.
+    Platform,
+    /// A hazard, a static sphere which zaps the player touching or hooking it so they can't hook for a while.
+    ElectricSphere,
+    /// A hazard, a heavy block falling fast which pushes the player down.
+    Anvil,
+    /// A pickup worth a single coin.
+    CandyCoin,
+    /// A rare pickup worth a lot of coins.
+    GoldenGumball,
+}
+
+#[derive(Default)]
+pub struct SpawnTableLoader;
+impl AssetLoader for SpawnTableLoader {
+    type Asset = SpawnTable;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SpawnTable, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut table: SpawnTable = ron::de::from_bytes(&bytes)?;
+            for entry in table.groups.iter_mut().flat_map(|group| &mut group.objects) {
+                entry.structure_handles = entry
+                    .structures
+                    .iter()
+                    .map(|path| load_context.load(path))
+                    .collect();
+            }
+            for object in &mut table.chunk_objects {
+                object.structure_handles = object
+                    .structures
+                    .iter()
+                    .map(|path| load_context.load(path))
+                    .collect();
+            }
+            Ok(table)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spawn_table.ron"]
+    }
+}
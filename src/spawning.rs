@@ -1,20 +1,96 @@
+use std::f32::consts::FRAC_PI_2;
+
 use instant::Duration;
 
-use bevy::{pbr::DirectionalLightShadowMap, prelude::*};
+use bevy::{
+    ecs::system::SystemParam,
+    pbr::DirectionalLightShadowMap,
+    prelude::*,
+    render::{batching::NoAutomaticBatching, view::NoFrustumCulling},
+    utils::{HashMap, HashSet},
+};
 use bevy_toon_shader::{ToonShaderMaterial, ToonShaderSun};
 use bevy_xpbd_3d::prelude::*;
-use rand::Rng;
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
 
-use crate::{materials::OutlineToonMaterial, player::Player, GameState};
+use crate::{
+    biomes::{BiomeSpawnTables, CurrentBiome, BIOMES},
+    chunks::WorldChunks,
+    gameplay_running,
+    gumball_kinds::GumballKind,
+    gumball_pool::{GumballPool, Pooled},
+    hazards::{HazardMaterials, ANVIL_SIZE, ANVIL_SPEED},
+    instancing::{CubeInstance, CubeInstances},
+    materials::OutlineToonMaterial,
+    pickups::{Pickup, PickupMaterials, CANDY_COIN_VALUE, GOLDEN_GUMBALL_VALUE},
+    platforms::Platform,
+    player::Player,
+    seed::WorldRng,
+    spatial_hash::SpatialHash,
+    spawn_table::{Archetype, ObjectSettings, SpawnTable, SpawnTableLoader},
+    structure::{Structure, StructureLoader, VoxelPoints},
+    DespawnOnRunEnd, GameState,
+};
 
 pub const DESPAWN_Y: f32 = -100.0;
-pub const MIN_SPHERE_DISTANCE: f32 = 3000.0;
-pub const MIN_THINGAMAJIG_DISTANCE: f32 = 20000.0;
+/// The size of platforms along every axis.
+pub const PLATFORM_SIZE: Vec3 = Vec3::new(12.0, 1.0, 12.0);
+/// Each group of the spawn table spawns at most this many objects per fixed timestep.
+/// How many timesteps a single frame catches up on is already limited by the max delta of `Time<Virtual>`.
+pub const MAX_SPAWNS_PER_TICK: u32 = 3;
+
+/// When each group of the spawn table spawns next, in seconds of elapsed time.
+#[derive(Resource, Default)]
+pub struct SpawnTimers(Vec<f64>);
 
+/// How many spawns were dropped this run because spawning fell too far behind, like after a long frame.
+#[derive(Resource, Default)]
+pub struct SkippedSpawns(pub u64);
+
+/// The meshes shared by all spawned objects.
 #[derive(Resource)]
-pub struct SpawnSettings {
-    lvl1_spawn: f64,
-    lvl2_spawn: f64,
+pub struct SpawnMeshes {
+    cube: Handle<Mesh>,
+    gumball: Handle<Mesh>,
+    sphere: Handle<Mesh>,
+    platform: Handle<Mesh>,
+    anvil: Handle<Mesh>,
+    candy_coin: Handle<Mesh>,
+}
+impl FromWorld for SpawnMeshes {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        Self {
+            cube: meshes.add(shape::Cube::new(1.0).into()),
+            gumball: meshes.add(
+                shape::UVSphere {
+                    radius: 1.0,
+                    sectors: 24,
+                    stacks: 12,
+                }
+                .into(),
+            ),
+            sphere: meshes.add(
+                shape::UVSphere {
+                    radius: 3.0,
+                    sectors: 72,
+                    stacks: 36,
+                }
+                .into(),
+            ),
+            platform: meshes
+                .add(shape::Box::new(PLATFORM_SIZE.x, PLATFORM_SIZE.y, PLATFORM_SIZE.z).into()),
+            candy_coin: meshes.add(
+                shape::Cylinder {
+                    radius: 1.0,
+                    height: 0.3,
+                    ..default()
+                }
+                .into(),
+            ),
+            anvil: meshes.add(shape::Box::new(ANVIL_SIZE.x, ANVIL_SIZE.y, ANVIL_SIZE.z).into()),
+        }
+    }
 }
 
 #[derive(Component)]
@@ -24,20 +100,22 @@ pub struct SpawnPlugin;
 impl Plugin for SpawnPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(DirectionalLightShadowMap { size: 4096 })
-            .insert_resource(SpawnSettings {
-                lvl1_spawn: 0.02,
-                lvl2_spawn: 0.0,
-            })
+            .init_asset::<SpawnTable>()
+            .init_asset_loader::<SpawnTableLoader>()
+            .init_asset::<Structure>()
+            .init_asset_loader::<StructureLoader>()
+            .init_resource::<SpawnMeshes>()
+            .init_resource::<SpawnTimers>()
+            .init_resource::<SkippedSpawns>()
             .add_systems(OnEnter(GameState::Playing), setup)
+            .add_systems(FixedUpdate, spawn_falling_objects.run_if(gameplay_running))
             .add_systems(
                 Update,
-                ((
-                    spawn_falling_objects,
-                    despawn_falling_objects,
-                    modify_spawn_settings,
-                    handle_fade_outs,
-                )
-                    .run_if(in_state(GameState::Playing)),),
+                ((despawn_falling_objects, handle_fade_outs).run_if(gameplay_running),),
+            )
+            .add_systems(
+                PostUpdate,
+                update_thingamajig_instances.after(PhysicsSet::Sync),
             );
     }
 }
@@ -60,6 +138,7 @@ pub fn setup(
             ..default()
         },
         ToonShaderSun,
+        DespawnOnRunEnd,
     ));
     commands.spawn((
         MaterialMeshBundle {
@@ -73,74 +152,218 @@ pub fn setup(
         },
         RigidBody::Static,
         Collider::cuboid(10.0, 1.0, 10.0),
+        DespawnOnRunEnd,
     ));
 }
 
 #[derive(Component)]
 pub struct StaticSphere;
 
+/// The directions to the cells which hold onto a voxel.
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// A destructible structure of voxels, which breaks apart where the player hits it.
 #[derive(Component)]
-pub struct Thingajamig(pub Vec<Entity>);
+pub struct Thingajamig {
+    /// The voxels still attached to the structure, by their cell in it.
+    pub attached: HashMap<UVec3, AttachedVoxel>,
+    /// The voxels knocked free, drawn by this entity until they despawn.
+    pub loose: Vec<Entity>,
+    /// The static body holding the collider of the attached voxels, it's hookable just like any other object.
+    pub body: Entity,
+    /// The position of the body.
+    origin: Vec3,
+}
+impl Thingajamig {
+    /// Detaches the voxels within `radius` of `point`.
+    /// Returns them, along with the voxels left without a connection to an anchor because of it.
+    pub fn break_at(
+        &mut self,
+        point: Vec3,
+        radius: f32,
+    ) -> (Vec<AttachedVoxel>, Vec<AttachedVoxel>) {
+        let broken = self.detach(|_, voxel| voxel.position.distance(point) < radius);
+        if broken.is_empty() {
+            return (broken, Vec::new());
+        }
+        // Flood fills from the anchors, everything it doesn't reach falls.
+        let mut connected = HashSet::new();
+        let mut stack: Vec<UVec3> = self
+            .attached
+            .iter()
+            .filter(|(_, voxel)| voxel.anchor)
+            .map(|(cell, _)| *cell)
+            .collect();
+        while let Some(cell) = stack.pop() {
+            if !connected.insert(cell) {
+                continue;
+            }
+            for direction in NEIGHBOURS {
+                // Cells below zero wrap around, so they're never part of the structure.
+                let neighbour = (cell.as_ivec3() + direction).as_uvec3();
+                if self.attached.contains_key(&neighbour) && !connected.contains(&neighbour) {
+                    stack.push(neighbour);
+                }
+            }
+        }
+        let falling = self.detach(|cell, _| !connected.contains(cell));
+        (broken, falling)
+    }
+
+    fn detach(&mut self, filter: impl Fn(&UVec3, &AttachedVoxel) -> bool) -> Vec<AttachedVoxel> {
+        let cells: Vec<UVec3> = self
+            .attached
+            .iter()
+            .filter(|(cell, voxel)| filter(cell, voxel))
+            .map(|(cell, _)| *cell)
+            .collect();
+        let detached: Vec<AttachedVoxel> = cells
+            .iter()
+            .filter_map(|cell| self.attached.remove(cell))
+            .collect();
+        self.loose.extend(detached.iter().map(|voxel| voxel.entity));
+        detached
+    }
+
+    /// The collider of the attached voxels relative to the body, or `None` if there are none left.
+    pub fn collider(&self) -> Option<Collider> {
+        if self.attached.is_empty() {
+            return None;
+        }
+        Some(Collider::compound(
+            self.attached
+                .values()
+                .map(|voxel| {
+                    (
+                        voxel.position - self.origin,
+                        Quat::IDENTITY,
+                        Collider::cuboid(1.5, 1.5, 1.5),
+                    )
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct AttachedVoxel {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub anchor: bool,
+}
+
+/// The color of a cube drawn with [`CubeInstances`].
+#[derive(Component)]
+pub struct CubeColor(pub Color);
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_falling_objects(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut toon_materials: ResMut<Assets<OutlineToonMaterial>>,
+    mut spawner: ObjectSpawner,
     time: Res<Time>,
     physics_time: Res<Time<Physics>>,
     player: Query<&Transform, With<Player>>,
-    mut last_time_elapsed_lvl1: Local<f64>,
-    mut last_time_elapsed_lvl2: Local<f64>,
-    spheres: Query<&Transform, With<StaticSphere>>,
-    thingamajigs: Query<&Transform, With<Thingajamig>>,
-    spawn_settings: Res<SpawnSettings>,
-    mut mesh_material_container: Local<Option<(Handle<Mesh>, Handle<Mesh>, Handle<Mesh>)>>,
+    spawn_tables: Res<Assets<SpawnTable>>,
+    biome_spawn_tables: Res<BiomeSpawnTables>,
+    biome: Res<CurrentBiome>,
+    mut timers: ResMut<SpawnTimers>,
+    mut skipped: ResMut<SkippedSpawns>,
+    mut world_rng: ResMut<WorldRng>,
+    mut chunks: ResMut<WorldChunks>,
 ) {
+    let Some(spawn_table) = spawn_tables.get(&biome_spawn_tables.0[biome.0]) else {
+        return;
+    };
+    let now = time.elapsed_seconds_f64();
+    // The table can change size when it's reloaded.
+    timers.0.resize(spawn_table.groups.len(), now);
     if physics_time.is_paused() {
-        *last_time_elapsed_lvl1 = time.elapsed_seconds_f64();
-        *last_time_elapsed_lvl2 = time.elapsed_seconds_f64();
+        timers.0.fill(now);
         return;
     }
     let player_transform = player.single();
-    let mut rand = rand::thread_rng();
 
-    if mesh_material_container.is_none() {
-        *mesh_material_container = Some((
-            meshes.add(shape::Cube::new(1.0).into()),
-            meshes.add(
-                shape::UVSphere {
-                    radius: 1.0,
-                    sectors: 24,
-                    stacks: 12,
-                }
-                .into(),
-            ),
-            meshes.add(
-                shape::UVSphere {
-                    radius: 3.0,
-                    sectors: 72,
-                    stacks: 36,
-                }
-                .into(),
-            ),
-        ));
+    for (i, group) in spawn_table.groups.iter().enumerate() {
+        let Some(interval) = group.interval(player_transform.translation.y) else {
+            timers.0[i] = now;
+            continue;
+        };
+        let Ok(weights) = WeightedIndex::new(group.objects.iter().map(|entry| entry.weight)) else {
+            warn!("Spawn group {i} has no objects with a positive weight");
+            continue;
+        };
+        let rand = world_rng.stream(i);
+        let mut spawns = 0;
+        while timers.0[i] < now {
+            if spawns == MAX_SPAWNS_PER_TICK {
+                // Catching up on everything at once would stall the next frames too, so the rest is dropped.
+                let behind = ((now - timers.0[i]) / interval).ceil();
+                skipped.0 += behind as u64;
+                timers.0[i] += behind * interval;
+                break;
+            }
+            spawns += 1;
+            timers.0[i] += interval;
+
+            let entry = &group.objects[weights.sample(rand)];
+            let pos = player_transform.translation + entry.volume.sample(rand);
+            let too_close = spawner
+                .spatial_hash
+                .within(entry.archetype, pos, entry.min_spacing)
+                .next()
+                .is_some();
+            if too_close {
+                continue;
+            }
+            let entities = spawner.spawn(entry.settings(), biome.0, rand, pos);
+            chunks.add(WorldChunks::chunk_at(pos), entities);
+        }
     }
-    let (cube_mesh, sphere_1_mesh, sphere_3_mesh) = mesh_material_container.as_ref().unwrap();
+}
 
-    while *last_time_elapsed_lvl1 < time.elapsed_seconds_f64() {
-        if rand.gen_bool(0.1) {
-            let pos = Vec3::new(
-                rand.gen_range(-100.0..100.0) + player_transform.translation.x,
-                rand.gen_range(-100.0..100.0) + player_transform.translation.y,
-                rand.gen_range(-100.0..100.0) + player_transform.translation.z,
-            );
-            let other_sphere_nearby = spheres
-                .iter()
-                .any(|v| v.translation.distance_squared(pos) < MIN_SPHERE_DISTANCE);
-            if !other_sphere_nearby {
-                commands.spawn((
+/// Everything needed to spawn the objects of spawn tables.
+#[derive(SystemParam)]
+pub struct ObjectSpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    pub spatial_hash: ResMut<'w, SpatialHash>,
+    pub pool: ResMut<'w, GumballPool>,
+    pub toon_materials: ResMut<'w, Assets<OutlineToonMaterial>>,
+    pub hazard_materials: Res<'w, HazardMaterials>,
+    pub pickup_materials: Res<'w, PickupMaterials>,
+    pub meshes: Res<'w, SpawnMeshes>,
+    pub structures: Res<'w, Assets<Structure>>,
+}
+impl ObjectSpawner<'_, '_> {
+    /// Spawns an object at `pos` and adds it to the spatial hash, returning all the entities it's made of.
+    pub fn spawn(
+        &mut self,
+        object: ObjectSettings,
+        biome: usize,
+        rand: &mut impl Rng,
+        pos: Vec3,
+    ) -> Vec<Entity> {
+        let Self {
+            commands,
+            spatial_hash,
+            pool,
+            toon_materials,
+            hazard_materials,
+            pickup_materials,
+            meshes,
+            structures,
+        } = self;
+        let entities = match object.archetype {
+            Archetype::Nothing => Vec::new(),
+            Archetype::Sphere => vec![commands
+                .spawn((
                     MaterialMeshBundle {
-                        mesh: sphere_3_mesh.clone(),
+                        mesh: meshes.sphere.clone(),
                         material: toon_materials.add(OutlineToonMaterial {
                             color: Color::GRAY,
                             outline_color: Color::NONE,
@@ -152,180 +375,292 @@ pub fn spawn_falling_objects(
                     RigidBody::Static,
                     Collider::ball(3.0),
                     DespawnOnLowerThanY,
+                    DespawnOnRunEnd,
+                    StaticSphere,
+                    Archetype::Sphere,
+                ))
+                .id()],
+            Archetype::ElectricSphere => vec![commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.sphere.clone(),
+                        material: hazard_materials.electric.plain.clone(),
+                        transform: Transform::from_translation(pos),
+                        ..default()
+                    },
+                    hazard_materials.electric.clone(),
+                    RigidBody::Static,
+                    Collider::ball(3.0),
+                    DespawnOnLowerThanY,
+                    DespawnOnRunEnd,
                     StaticSphere,
-                ));
+                    Archetype::ElectricSphere,
+                ))
+                .id()],
+            Archetype::Anvil => vec![commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.anvil.clone(),
+                        material: hazard_materials.anvil.plain.clone(),
+                        transform: Transform::from_translation(pos),
+                        ..default()
+                    },
+                    hazard_materials.anvil.clone(),
+                    RigidBody::Dynamic,
+                    LinearVelocity(Vec3::Y * -ANVIL_SPEED),
+                    Collider::cuboid(ANVIL_SIZE.x, ANVIL_SIZE.y, ANVIL_SIZE.z),
+                    ColliderDensity(10.0),
+                    DespawnOnLowerThanY,
+                    DespawnOnRunEnd,
+                    Archetype::Anvil,
+                ))
+                .id()],
+            Archetype::Gumball => {
+                let kind = GumballKind::pick(object.gumball_kinds, rand);
+                let material = pool.material(kind, biome, rand);
+                let gumball = (
+                    material.plain.clone(),
+                    material,
+                    kind,
+                    kind.restitution(),
+                    Transform::from_translation(pos),
+                    RigidBody::Dynamic,
+                    LinearVelocity(Vec3::Y * -10.0),
+                    AngularVelocity::ZERO,
+                    Collider::ball(1.0),
+                    DespawnOnLowerThanY,
+                    Archetype::Gumball,
+                );
+                let entity = match pool.take() {
+                    Some(entity) => commands
+                        .entity(entity)
+                        .insert((gumball, Visibility::Inherited))
+                        .id(),
+                    None => commands
+                        .spawn((
+                            gumball,
+                            meshes.gumball.clone(),
+                            GlobalTransform::default(),
+                            VisibilityBundle::default(),
+                            Pooled,
+                            DespawnOnRunEnd,
+                        ))
+                        .id(),
+                };
+                vec![entity]
             }
-        } else {
-            commands.spawn((
-                MaterialMeshBundle {
-                    mesh: sphere_1_mesh.clone(),
-                    material: toon_materials.add(OutlineToonMaterial {
-                        color: Color::rgb_linear(
-                            rand.gen_range(0.3..10.0),
-                            rand.gen_range(0.3..10.0),
-                            rand.gen_range(0.3..10.0),
-                        ),
-                        outline_color: Color::NONE,
+            Archetype::CandyCoin => vec![commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.candy_coin.clone(),
+                        material: pickup_materials.candy_coin.clone(),
+                        // Standing upright, like a coin rolling along.
+                        transform: Transform::from_translation(pos)
+                            .with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
                         ..default()
-                    }),
-                    transform: Transform::from_xyz(
-                        rand.gen_range(-200.0..200.0) + player_transform.translation.x,
-                        100.0 + player_transform.translation.y,
-                        rand.gen_range(-200.0..200.0) + player_transform.translation.z,
-                    ),
-                    ..default()
-                },
-                RigidBody::Dynamic,
-                LinearVelocity(Vec3::Y * -10.0),
-                Collider::ball(1.0),
-                DespawnOnLowerThanY,
-            ));
+                    },
+                    Pickup {
+                        value: CANDY_COIN_VALUE,
+                    },
+                    DespawnOnLowerThanY,
+                    DespawnOnRunEnd,
+                    Archetype::CandyCoin,
+                ))
+                .id()],
+            Archetype::GoldenGumball => vec![commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.gumball.clone(),
+                        material: pickup_materials.golden_gumball.clone(),
+                        transform: Transform::from_translation(pos),
+                        ..default()
+                    },
+                    Pickup {
+                        value: GOLDEN_GUMBALL_VALUE,
+                    },
+                    DespawnOnLowerThanY,
+                    DespawnOnRunEnd,
+                    Archetype::GoldenGumball,
+                ))
+                .id()],
+            Archetype::Platform => {
+                let platform = Platform {
+                    motion: object.motion.clone(),
+                    origin: pos,
+                    time: 0.0,
+                };
+                vec![commands
+                    .spawn((
+                        MaterialMeshBundle {
+                            mesh: meshes.platform.clone(),
+                            material: toon_materials.add(OutlineToonMaterial {
+                                color: BIOMES[biome].object_colors.sample(rand),
+                                outline_color: Color::NONE,
+                                ..default()
+                            }),
+                            transform: Transform::from_translation(platform.position()),
+                            ..default()
+                        },
+                        RigidBody::Kinematic,
+                        Collider::cuboid(PLATFORM_SIZE.x, PLATFORM_SIZE.y, PLATFORM_SIZE.z),
+                        DespawnOnLowerThanY,
+                        DespawnOnRunEnd,
+                        platform,
+                        Archetype::Platform,
+                    ))
+                    .id()]
+            }
+            Archetype::Thingamajig => {
+                let Some(structure) = object
+                    .structure_handles
+                    .choose(rand)
+                    .and_then(|handle| structures.get(handle))
+                else {
+                    return Vec::new();
+                };
+                let color = BIOMES[biome].object_colors.sample(rand);
+                spawn_thingamajig(commands, toon_materials, meshes, structure, color, pos)
+            }
+        };
+        // The object itself always comes last.
+        if let Some(entity) = entities.last() {
+            spatial_hash.insert(*entity, object.archetype, pos);
         }
+        entities
+    }
+}
 
-        *last_time_elapsed_lvl1 += spawn_settings.lvl1_spawn;
+/// Spawns the voxels of `structure` around `pos`, voxels without their own color use `color`.
+///
+/// The voxels are drawn together by the thingamajig, and the attached ones share one compound collider.
+/// Returns its voxels, its body and then the thingamajig itself.
+fn spawn_thingamajig(
+    commands: &mut Commands,
+    toon_materials: &mut Assets<OutlineToonMaterial>,
+    meshes: &SpawnMeshes,
+    structure: &Structure,
+    color: Color,
+    pos: Vec3,
+) -> Vec<Entity> {
+    let mut attached = HashMap::with_capacity(structure.voxels.len());
+    let mut instances = Vec::with_capacity(structure.voxels.len());
+    for voxel in &structure.voxels {
+        let kind = &structure.palette[voxel.kind];
+        let color = kind
+            .color
+            .map_or(color, |(r, g, b)| Color::rgb_linear(r, g, b));
+        let transform = Transform::from_translation(pos + voxel.offset);
+        let entity = commands
+            .spawn((
+                TransformBundle::from_transform(transform),
+                CubeColor(color),
+                VoxelPoints(kind.points),
+                DespawnOnLowerThanY,
+                DespawnOnRunEnd,
+            ))
+            .id();
+        attached.insert(
+            voxel.cell,
+            AttachedVoxel {
+                entity,
+                position: transform.translation,
+                anchor: kind.anchor,
+            },
+        );
+        instances.push(CubeInstance::new(&transform, color));
     }
-    if spawn_settings.lvl2_spawn == 0.0 {
-        *last_time_elapsed_lvl2 = time.elapsed_seconds_f64();
+    let material = toon_materials.add(OutlineToonMaterial {
+        color: Color::WHITE,
+        outline_color: Color::NONE,
+        ..default()
+    });
+    // The material is shared with the voxels, so hooking the body outlines all of them.
+    let mut body = commands.spawn((
+        RigidBody::Static,
+        material.clone(),
+        TransformBundle::from_transform(Transform::from_translation(pos)),
+        DespawnOnLowerThanY,
+        DespawnOnRunEnd,
+    ));
+    let thingamajig = Thingajamig {
+        attached,
+        loose: Vec::new(),
+        body: body.id(),
+        origin: pos,
+    };
+    if let Some(collider) = thingamajig.collider() {
+        body.insert(collider);
     }
-    while *last_time_elapsed_lvl2 < time.elapsed_seconds_f64() {
-        if rand.gen_bool(0.1) {
-            let pos = Vec3::new(
-                rand.gen_range(-200.0..200.0) + player_transform.translation.x,
-                100.0 + player_transform.translation.y,
-                rand.gen_range(-200.0..200.0) + player_transform.translation.z,
-            );
-            let other_thingamajig_nearby = thingamajigs
-                .iter()
-                .any(|v| v.translation.distance_squared(pos) < MIN_THINGAMAJIG_DISTANCE);
-            let material_color = Color::rgb_linear(
-                rand.gen_range(0.3..10.0),
-                rand.gen_range(0.3..10.0),
-                rand.gen_range(0.3..10.0),
-            );
-            let material = toon_materials.add(OutlineToonMaterial {
-                color: material_color,
-                outline_color: Color::NONE,
-                ..default()
-            });
-            if !other_thingamajig_nearby {
-                info!("spawning!");
-                let mut entities = Vec::with_capacity(10 * 10 * 6);
-                if rand.gen_bool(0.5) {
-                    for x in -5..5 {
-                        for y in -5..5 {
-                            for z in -3..3 {
-                                entities.push(
-                                    commands
-                                        .spawn((
-                                            MaterialMeshBundle {
-                                                mesh: cube_mesh.clone(),
-                                                material: material.clone(),
-                                                transform: Transform::from_xyz(
-                                                    pos.x + (x as f32 * 2.0) + 2.0,
-                                                    pos.y + (y as f32 * 2.0) + 2.0,
-                                                    pos.z + (z as f32 * 2.0) + 2.0,
-                                                ),
-                                                ..default()
-                                            },
-                                            RigidBody::Static,
-                                            GravityScale(0.0),
-                                            Collider::cuboid(1.5, 1.5, 1.5),
-                                            ColliderDensity(0.25),
-                                            DespawnOnLowerThanY,
-                                            Sleeping,
-                                        ))
-                                        .id(),
-                                );
-                            }
-                        }
-                    }
-                    commands.spawn((
-                        DespawnOnLowerThanY,
-                        Thingajamig(entities),
-                        Collider::cuboid(20.0 + 4.0, 20.0 + 4.0, 12.0 + 4.0),
-                        TransformBundle::from_transform(Transform::from_translation(pos)),
-                    ));
-                } else {
-                    for x in -4..4 {
-                        for y in -4..4 {
-                            for z in -4..4 {
-                                if Vec3::new(x as f32, y as f32, z as f32).length() < 5.0 {
-                                    entities.push(
-                                        commands
-                                            .spawn((
-                                                MaterialMeshBundle {
-                                                    mesh: cube_mesh.clone(),
-                                                    material: material.clone(),
-                                                    transform: Transform::from_xyz(
-                                                        pos.x + (x as f32 * 2.0) + 2.0,
-                                                        pos.y + (y as f32 * 2.0) + 2.0,
-                                                        pos.z + (z as f32 * 2.0) + 2.0,
-                                                    ),
-                                                    ..default()
-                                                },
-                                                RigidBody::Static,
-                                                GravityScale(0.0),
-                                                Collider::cuboid(1.5, 1.5, 1.5),
-                                                ColliderDensity(0.25),
-                                                DespawnOnLowerThanY,
-                                                Sleeping,
-                                            ))
-                                            .id(),
-                                    );
-                                }
-                            }
-                        }
-                    }
-                    commands.spawn((
-                        DespawnOnLowerThanY,
-                        Thingajamig(entities),
-                        Collider::cuboid(16.0 + 4.0, 16.0 + 4.0, 16.0 + 4.0),
-                        TransformBundle::from_transform(Transform::from_translation(pos)),
-                    ));
-                }
-            }
+    let mut entities: Vec<Entity> = thingamajig
+        .attached
+        .values()
+        .map(|voxel| voxel.entity)
+        .collect();
+    entities.push(thingamajig.body);
+    entities.push(
+        commands
+            .spawn((
+                meshes.cube.clone(),
+                CubeInstances {
+                    material,
+                    instances,
+                },
+                SpatialBundle::from_transform(Transform::from_translation(pos)),
+                NoFrustumCulling,
+                NoAutomaticBatching,
+                DespawnOnLowerThanY,
+                DespawnOnRunEnd,
+                thingamajig,
+                Archetype::Thingamajig,
+            ))
+            .id(),
+    );
+    entities
+}
+
+/// Updates the drawn voxels of thingamajigs, as they break apart and their loose voxels fly off.
+fn update_thingamajig_instances(
+    mut thingamajigs: Query<(&mut Thingajamig, &mut CubeInstances)>,
+    voxels: Query<(&Transform, &CubeColor)>,
+) {
+    for (mut thingamajig, mut cubes) in thingamajigs.iter_mut() {
+        // Attached voxels don't move, so there's nothing to update until some break off.
+        if thingamajig.loose.is_empty() && !thingamajig.is_changed() {
+            continue;
         }
-        *last_time_elapsed_lvl2 += spawn_settings.lvl2_spawn;
+        let thingamajig = thingamajig.bypass_change_detection();
+        thingamajig.loose.retain(|voxel| voxels.contains(*voxel));
+        let entities = thingamajig
+            .attached
+            .values()
+            .map(|voxel| voxel.entity)
+            .chain(thingamajig.loose.iter().copied());
+        cubes.instances = voxels
+            .iter_many(entities)
+            .map(|(transform, CubeColor(color))| CubeInstance::new(transform, *color))
+            .collect();
     }
 }
 
 fn despawn_falling_objects(
     mut commands: Commands,
     player: Query<&Transform, With<Player>>,
-    query: Query<(Entity, &Transform), With<DespawnOnLowerThanY>>,
+    query: Query<(Entity, &Transform, Has<Pooled>), With<DespawnOnLowerThanY>>,
+    mut pool: ResMut<GumballPool>,
 ) {
     let player_transform = player.single();
-    for (entity, transform) in query.iter() {
+    for (entity, transform, pooled) in query.iter() {
         if transform.translation.y < DESPAWN_Y + player_transform.translation.y {
-            commands.entity(entity).despawn();
+            if pooled {
+                pool.release(&mut commands, entity);
+            } else {
+                commands.entity(entity).despawn();
+            }
         }
     }
 }
 
-fn modify_spawn_settings(
-    mut settings: ResMut<SpawnSettings>,
-    player: Query<&Transform, With<Player>>,
-) {
-    let player_y = player.single().translation.y;
-
-    // trace!("Player Y {player_y:?}");
-    // higher number means more time between spawns
-    if player_y > 500.0 {
-        settings.lvl1_spawn = 0.08 * (player_y as f64 / 300.0);
-        settings.lvl2_spawn = 0.50 * (player_y as f64 / 300.0);
-    } else if player_y > 300.0 {
-        settings.lvl1_spawn = 0.08;
-        settings.lvl2_spawn = 0.50;
-    } else if player_y > 200.0 {
-        settings.lvl2_spawn = 0.0;
-        settings.lvl1_spawn = 0.07;
-    } else if player_y > 100.0 {
-        settings.lvl1_spawn = 0.04;
-    } else {
-        settings.lvl1_spawn = 0.02;
-    }
-}
-
+/// Fades out a [`CubeColor`] over `duration`, then despawns the cube.
 #[derive(Component)]
 pub struct OutlineToonFadeOut {
     pub duration: Duration,
@@ -342,22 +677,15 @@ impl OutlineToonFadeOut {
 
 fn handle_fade_outs(
     mut commands: Commands,
-    mut fadeouts: Query<(
-        Entity,
-        &mut OutlineToonFadeOut,
-        &Handle<OutlineToonMaterial>,
-    )>,
+    mut fadeouts: Query<(Entity, &mut OutlineToonFadeOut, &mut CubeColor)>,
     time: Res<Time>,
     physics_time: Res<Time<Physics>>,
-    mut toon_materials: ResMut<Assets<OutlineToonMaterial>>,
 ) {
     if physics_time.is_paused() {
         return;
     }
-    for (entity, mut fadeout, material_handle) in fadeouts.iter_mut() {
-        if let Some(toon_material) = toon_materials.get_mut(material_handle) {
-            toon_material.color.set_a(fadeout.alpha);
-        }
+    for (entity, mut fadeout, mut color) in fadeouts.iter_mut() {
+        color.0.set_a(fadeout.alpha);
         fadeout.alpha -= time.delta_seconds() / fadeout.duration.as_secs_f32();
         if fadeout.alpha < 0.0 {
             commands.entity(entity).despawn_recursive();
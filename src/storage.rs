@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Where a file is stored on desktop. The web build keeps everything in browser local storage.
+#[derive(Clone, Copy)]
+enum Directory {
+    /// Options like the settings and input bindings.
+    Config,
+    /// Things the game keeps track of, like the high scores.
+    Data,
+}
+
+/// Loads `name` from the config directory (or browser local storage on the web).
+///
+/// Returns `None` if it doesn't exist yet or can't be read.
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    load_from(Directory::Config, name)
+}
+
+/// Saves `value` as `name` in the config directory (or browser local storage on the web).
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    save_to(Directory::Config, name, value);
+}
+
+/// Loads `name` from the data directory (or browser local storage on the web).
+///
+/// Returns `None` if it doesn't exist yet or can't be read.
+pub fn load_data<T: DeserializeOwned>(name: &str) -> Option<T> {
+    load_from(Directory::Data, name)
+}
+
+/// Saves `value` as `name` in the data directory (or browser local storage on the web).
+pub fn save_data<T: Serialize>(name: &str, value: &T) {
+    save_to(Directory::Data, name, value);
+}
+
+fn load_from<T: DeserializeOwned>(directory: Directory, name: &str) -> Option<T> {
+    let contents = read(directory, name)?;
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Failed to parse {name}: {error}");
+            None
+        }
+    }
+}
+
+fn save_to<T: Serialize>(directory: Directory, name: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => write(directory, name, &contents),
+        Err(error) => warn!("Failed to serialize {name}: {error}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(directory: Directory, name: &str) -> Option<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "doonv", "Gumball Grappling")?;
+    let dir = match directory {
+        Directory::Config => dirs.config_dir(),
+        Directory::Data => dirs.data_dir(),
+    };
+    Some(dir.join(name).with_extension("ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(directory: Directory, name: &str) -> Option<String> {
+    std::fs::read_to_string(path(directory, name)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(directory: Directory, name: &str, contents: &str) {
+    let Some(path) = path(directory, name) else {
+        warn!("Couldn't find a directory to save {name} to");
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, contents));
+    if let Err(error) = result {
+        warn!("Failed to save {}: {error}", path.display());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(_directory: Directory, name: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("gumball_grappling/{name}"))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(_directory: Directory, name: &str, contents: &str) {
+    let Some(storage) = local_storage() else {
+        warn!("Local storage is unavailable, couldn't save {name}");
+        return;
+    };
+    if let Err(error) = storage.set_item(&format!("gumball_grappling/{name}"), contents) {
+        warn!("Failed to save {name}: {error:?}");
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+/// The distance between the centers of two neighbouring voxels.
+pub const VOXEL_SPACING: f32 = 2.0;
+
+/// A structure made of voxels, spawned as a thingamajig.
+///
+/// Loaded from `.structure.ron` files.
+#[derive(Asset, TypePath)]
+pub struct Structure {
+    /// The kinds of voxels in this structure.
+    pub palette: Vec<VoxelKind>,
+    pub voxels: Vec<Voxel>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Voxel {
+    /// The cell of the voxel in the grid of the structure, voxels in neighbouring cells hold onto each other.
+    pub cell: UVec3,
+    /// The position of the voxel relative to the center of the structure.
+    pub offset: Vec3,
+    /// The index of the voxel's kind in the palette.
+    pub kind: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct VoxelKind {
+    /// The color of the voxel in linear RGB, or `None` to use the color of the biome.
+    pub color: Option<(f32, f32, f32)>,
+    /// The destruction points this voxel is worth once it's knocked free.
+    pub points: u64,
+    /// Whether this voxel holds the structure in the air.
+    /// Voxels without a connection to an anchor fall, so structures without anchors fall apart on the first hit.
+    pub anchor: bool,
+}
+
+/// The file format of a [`Structure`].
+#[derive(Deserialize)]
+struct StructureFile {
+    /// Maps the characters used in `layers` to kinds of voxels.
+    palette: HashMap<char, VoxelKind>,
+    /// Horizontal slices of the structure from the bottom up.
+    /// Each one is a list of rows along the Z axis, and every character in a row is a voxel along the X axis.
+    /// Spaces and dots are empty.
+    layers: Vec<Vec<String>>,
+}
+
+impl TryFrom<StructureFile> for Structure {
+    type Error = String;
+
+    fn try_from(file: StructureFile) -> Result<Self, Self::Error> {
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        let mut cells = Vec::new();
+        for (y, layer) in file.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, character) in row.chars().enumerate() {
+                    if character == ' ' || character == '.' {
+                        continue;
+                    }
+                    let Some(kind) = file.palette.get(&character) else {
+                        return Err(format!("'{character}' isn't in the palette"));
+                    };
+                    let index = *indices.entry(character).or_insert_with(|| {
+                        palette.push(*kind);
+                        palette.len() - 1
+                    });
+                    cells.push((UVec3::new(x as u32, y as u32, z as u32), index));
+                }
+            }
+        }
+        let Some(max) = cells.iter().map(|(cell, _)| *cell).reduce(UVec3::max) else {
+            return Err("the structure has no voxels".to_string());
+        };
+        let min = cells
+            .iter()
+            .map(|(cell, _)| *cell)
+            .reduce(UVec3::min)
+            .unwrap_or_default();
+        let center = (min + max).as_vec3() / 2.0;
+        Ok(Self {
+            palette,
+            voxels: cells
+                .into_iter()
+                .map(|(cell, kind)| Voxel {
+                    cell,
+                    offset: (cell.as_vec3() - center) * VOXEL_SPACING,
+                    kind,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// How many destruction points a voxel of a thingamajig is worth.
+#[derive(Component)]
+pub struct VoxelPoints(pub u64);
+
+#[derive(Default)]
+pub struct StructureLoader;
+impl AssetLoader for StructureLoader {
+    type Asset = Structure;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Structure, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file: StructureFile = ron::de::from_bytes(&bytes)?;
+            Ok(Structure::try_from(file)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["structure.ron"]
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    actions::{Action, ActionSet, VirtualInput},
+    materials::RoundedRectangleMaterial,
+    shop::IsShopping,
+    DespawnOnRunEnd, GameState,
+};
+
+/// How far the joystick knob can move away from where the touch started, in pixels.
+pub const JOYSTICK_RADIUS: f32 = 60.0;
+/// How many radians the camera turns per pixel dragged on the right half of the screen.
+pub const TOUCH_LOOK_SENSITIVITY: f32 = 0.005;
+
+/// Whether the on-screen touch controls are in use.
+///
+/// They get activated by touching the screen and deactivated again by using the keyboard.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    pub active: bool,
+}
+
+#[derive(Component)]
+pub struct TouchControlsRoot;
+#[derive(Component)]
+pub struct JoystickBase;
+#[derive(Component)]
+pub struct JoystickKnob;
+/// An on-screen button which presses its [`Action`] while it's being touched.
+#[derive(Component)]
+pub struct TouchButton(pub Action);
+/// A [`TouchButton`] which is only shown while shopping.
+#[derive(Component)]
+pub struct ShopTouchButton;
+
+pub struct TouchPlugin;
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchControls>()
+            .add_systems(OnEnter(GameState::Playing), setup_touch_controls)
+            .add_systems(
+                PreUpdate,
+                (detect_touch, update_touch_controls)
+                    .chain()
+                    .after(InputSystem)
+                    .before(ActionSet)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (show_touch_controls, show_shop_touch_buttons).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+fn setup_touch_controls(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut rectangles: ResMut<Assets<RoundedRectangleMaterial>>,
+) {
+    let circle = |rectangles: &mut Assets<RoundedRectangleMaterial>, alpha: f32| {
+        rectangles.add(RoundedRectangleMaterial {
+            color: Color::rgba(1.0, 1.0, 1.0, alpha).into(),
+            roundedness: Vec2::ONE,
+        })
+    };
+    let button_material = circle(&mut rectangles, 0.2);
+    let joystick_material = circle(&mut rectangles, 0.15);
+    let knob_material = circle(&mut rectangles, 0.4);
+    let style = TextStyle {
+        font: asset_server.load("fonts/poppins/Poppins-Thin.ttf"),
+        font_size: 25.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            TouchControlsRoot,
+            DespawnOnRunEnd,
+        ))
+        .with_children(|commands| {
+            commands
+                .spawn((
+                    MaterialNodeBundle {
+                        material: joystick_material,
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Px(JOYSTICK_RADIUS * 2.0),
+                            height: Val::Px(JOYSTICK_RADIUS * 2.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    JoystickBase,
+                ))
+                .with_children(|commands| {
+                    commands.spawn((
+                        MaterialNodeBundle {
+                            material: knob_material,
+                            style: Style {
+                                width: Val::Px(JOYSTICK_RADIUS),
+                                height: Val::Px(JOYSTICK_RADIUS),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        JoystickKnob,
+                    ));
+                });
+
+            let buttons = [
+                (Action::Jump, "Jump", 110.0, Val::Px(30.0), Val::Px(30.0)),
+                (Action::Hook, "Hook", 110.0, Val::Px(160.0), Val::Px(60.0)),
+                (Action::Dash, "Dash", 90.0, Val::Px(50.0), Val::Px(160.0)),
+                (Action::Shop, "Shop", 70.0, Val::Px(30.0), Val::Auto),
+                (Action::Pause, "Pause", 70.0, Val::Px(110.0), Val::Auto),
+                (
+                    Action::SwitchHookMode,
+                    "Mode",
+                    70.0,
+                    Val::Px(290.0),
+                    Val::Px(40.0),
+                ),
+                // The upgrades, in the same order as in the shop.
+                (Action::Buy1, "Buy 1", 70.0, Val::Px(190.0), Val::Auto),
+                (Action::Buy2, "Buy 2", 70.0, Val::Px(110.0), Val::Auto),
+                (Action::Buy3, "Buy 3", 70.0, Val::Px(30.0), Val::Auto),
+            ];
+            for (action, label, size, right, bottom) in buttons {
+                let is_buy = matches!(action, Action::Buy1 | Action::Buy2 | Action::Buy3);
+                // Buttons without a bottom offset go in the top right corner instead,
+                // with the buy buttons in a row below the others.
+                let top = match (bottom, is_buy) {
+                    (Val::Auto, false) => Val::Px(30.0),
+                    (Val::Auto, true) => Val::Px(110.0),
+                    _ => Val::Auto,
+                };
+                let mut button = commands.spawn((
+                    MaterialNodeBundle {
+                        material: button_material.clone(),
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Px(size),
+                            height: Val::Px(size),
+                            right,
+                            bottom,
+                            top,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    TouchButton(action),
+                ));
+                button.with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(label, style.clone()));
+                });
+                if is_buy {
+                    button.insert(ShopTouchButton);
+                }
+            }
+        });
+}
+
+fn detect_touch(
+    mut controls: ResMut<TouchControls>,
+    touches: Res<Touches>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if touches.any_just_pressed() {
+        controls.active = true;
+    } else if keys.get_just_pressed().next().is_some() {
+        controls.active = false;
+    }
+}
+
+fn update_touch_controls(
+    controls: Res<TouchControls>,
+    touches: Res<Touches>,
+    window: Query<&Window>,
+    buttons: Query<(&TouchButton, &Node, &GlobalTransform, &InheritedVisibility)>,
+    mut joystick_base: Query<(&mut Style, &mut Visibility), With<JoystickBase>>,
+    mut joystick_knob: Query<&mut Style, (With<JoystickKnob>, Without<JoystickBase>)>,
+    mut virtual_input: ResMut<VirtualInput>,
+) {
+    virtual_input.pressed.clear();
+    virtual_input.movement = Vec2::ZERO;
+    virtual_input.look = Vec2::ZERO;
+    if !controls.active {
+        return;
+    }
+    let half_width = window
+        .get_single()
+        .map_or(0.0, |window| window.width() / 2.0);
+
+    let mut joystick = None;
+    for touch in touches.iter() {
+        let start = touch.start_position();
+        let button = buttons.iter().find(|(_, node, transform, visibility)| {
+            visibility.get() && node.logical_rect(transform).contains(start)
+        });
+        if let Some((TouchButton(action), _, _, _)) = button {
+            virtual_input.pressed.insert(*action);
+        } else if start.x < half_width {
+            // Only the first touch on the left half of the screen controls the joystick.
+            if joystick.is_none() {
+                let offset = (touch.position() - start).clamp_length_max(JOYSTICK_RADIUS);
+                virtual_input.movement = Vec2::new(offset.x, -offset.y) / JOYSTICK_RADIUS;
+                joystick = Some((start, offset));
+            }
+        } else {
+            let delta = touch.delta();
+            virtual_input.look += Vec2::new(delta.x, -delta.y) * TOUCH_LOOK_SENSITIVITY;
+        }
+    }
+
+    if let Ok((mut style, mut visibility)) = joystick_base.get_single_mut() {
+        if let Some((start, offset)) = joystick {
+            style.left = Val::Px(start.x - JOYSTICK_RADIUS);
+            style.top = Val::Px(start.y - JOYSTICK_RADIUS);
+            *visibility = Visibility::Inherited;
+            if let Ok(mut knob_style) = joystick_knob.get_single_mut() {
+                knob_style.left = Val::Px(offset.x);
+                knob_style.top = Val::Px(offset.y);
+            }
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+fn show_touch_controls(
+    controls: Res<TouchControls>,
+    mut root: Query<&mut Visibility, With<TouchControlsRoot>>,
+) {
+    for mut visibility in root.iter_mut() {
+        *visibility = if controls.active {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn show_shop_touch_buttons(
+    is_shopping: Res<IsShopping>,
+    mut buttons: Query<&mut Visibility, With<ShopTouchButton>>,
+) {
+    for mut visibility in buttons.iter_mut() {
+        *visibility = if is_shopping.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}